@@ -7,10 +7,18 @@ use std::collections::{HashMap, HashSet};
 use futures::future::try_join_all;
 use crate::log::print_errors;
 
-pub async fn get_url_data(url: Url) -> R<(Url, Vec<u8>)> {
+pub async fn get_url_data(url: Url, authorisation: Option<&HostAuthorisation>) -> R<(Url, Vec<u8>)> {
     println!("downloading data for url: {:?}", url);
+    let request = reqwest::Client::new().get(String::from(&url));
+    let authorised_request =
+      match authorisation.and_then(|a| a.token_for(&url)) {
+        Some(token) => request.bearer_auth(token.value()),
+        None => request
+      };
+
     let data =
-      reqwest::get(String::from(&url))
+      authorised_request
+      .send()
       .await
       .map_err(PursError::from)?
       .bytes()
@@ -20,7 +28,7 @@ pub async fn get_url_data(url: Url) -> R<(Url, Vec<u8>)> {
   Ok((url, data.to_vec()))
 }
 
-pub async fn get_or_create_avatar_file(avatar_info: &AvatarInfo) -> R<FileUrl> {
+pub async fn get_or_create_avatar_file(avatar_info: &AvatarInfo, authorisation: Option<&HostAuthorisation>) -> R<FileUrl> {
   let avatar_url = avatar_info.avatar_url();
   let user_id = &avatar_info.user_id();
   let cache_path = avatar_info.cache_path();
@@ -29,7 +37,7 @@ pub async fn get_or_create_avatar_file(avatar_info: &AvatarInfo) -> R<FileUrl> {
     CacheFileStatus::Exists => avatar_cache_file.url(),
     CacheFileStatus::DoesNotExist => {
       let downloaded_file =
-        get_url_data(avatar_url.clone())
+        get_url_data(avatar_url.clone(), authorisation)
           .and_then(|url_data|{
             async {
               save_avatar_data(&avatar_cache_file, url_data.1).await
@@ -93,7 +101,7 @@ pub async fn save_avatar_data(avatar_cache_file: &AvatarCacheFile, avatar_data:
   Ok(())
 }
 
-pub async fn get_avatars(comments: &Comments, avatar_cache_directory: &AvatarCacheDirectory) -> R<HashMap<Url, FileUrl>> {
+pub async fn get_avatars(comments: &Comments, avatar_cache_directory: &AvatarCacheDirectory, authorisation: Option<HostAuthorisation>) -> R<HashMap<Url, FileUrl>> {
   let mut unique_gravatar_urls: HashSet<AvatarInfo> = HashSet::new();
   comments
     .comments
@@ -115,7 +123,7 @@ pub async fn get_avatars(comments: &Comments, avatar_cache_directory: &AvatarCac
     });
 
   let url_data_handles = unique_gravatar_urls.into_iter().map(|u| {
-    tokio::task::spawn(get_avatar_from_cache(u))
+    tokio::task::spawn(get_avatar_from_cache(u, authorisation.clone()))
   });
 
   let url_data_results_with_errors =
@@ -133,9 +141,10 @@ pub async fn get_avatars(comments: &Comments, avatar_cache_directory: &AvatarCac
   Ok(url_data_results.into_iter().collect())
 }
 
-async fn get_avatar_from_cache(avatar_info: AvatarInfo) -> R<(Url, FileUrl)> {
+async fn get_avatar_from_cache(avatar_info: AvatarInfo, authorisation: Option<HostAuthorisation>) -> R<(Url, FileUrl)> {
   get_or_create_avatar_file(
-    &avatar_info
+    &avatar_info,
+    authorisation.as_ref()
   )
   .await
   .map(|file_url|{
@@ -1,7 +1,7 @@
 use wiremock::{MockServer, Mock, ResponseTemplate};
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{method, path, header};
 use crate::avatar::{get_url_data, does_cache_file_exist, get_or_create_avatar_file};
-use crate::model::{Url, AvatarCacheFile, CacheFileStatus, UserId, AvatarCacheDirectory, AvatarInfo};
+use crate::model::{Url, AvatarCacheFile, CacheFileStatus, UserId, AvatarCacheDirectory, AvatarInfo, ApiUrl, GitHubToken, HostAuthorisation};
 use pretty_assertions::assert_eq;
 use tempfile::tempdir;
 use std::fs::File;
@@ -25,12 +25,48 @@ async fn test_get_url_data() {
         .await;
 
     let url = Url::new(format!("{}/u/12345?v=4", &mock_server.uri()));
-    let result = get_url_data(url).await.unwrap();
+    let result = get_url_data(url, None).await.unwrap();
 
     assert_eq!(result.1, data)
 }
 
 
+#[tokio::test]
+async fn test_get_url_data_from_enterprise_host() {
+    let mock_server = MockServer::start().await;
+    let data = "1234".as_bytes();
+
+    Mock::given(method("GET"))
+        .and(path("/avatars/u/12345"))
+        .and(header("Authorization", "Bearer ghes-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(data))
+        .mount(&mock_server)
+        .await;
+
+    let api_url = ApiUrl::new(&format!("{}/api/v3", &mock_server.uri())).unwrap();
+    let authorisation = HostAuthorisation::new(&api_url, GitHubToken::new("ghes-token"));
+    let url = Url::new(format!("{}/avatars/u/12345?v=4", &mock_server.uri()));
+    let result = get_url_data(url, Some(&authorisation)).await.unwrap();
+
+    assert_eq!(result.1, data)
+}
+
+#[test]
+fn test_host_authorisation_only_applies_to_enterprise_host() {
+    let api_url = ApiUrl::new("https://github.acme.com/api/v3").unwrap();
+    let authorisation = HostAuthorisation::new(&api_url, GitHubToken::new("ghes-token"));
+
+    let token_for = |url: &str| authorisation.token_for(&Url::new(url.to_owned())).map(|t| t.value().to_owned());
+
+    assert_eq!(token_for("https://github.acme.com/avatars/u/1"), Some("ghes-token".to_owned()));
+    assert_eq!(token_for("https://avatars.github.acme.com/u/1"), Some("ghes-token".to_owned()));
+    assert_eq!(token_for("https://avatars.githubusercontent.com/u/1"), None);
+    assert_eq!(token_for("https://evilgithub.acme.com/u/1"), None);
+    // The token is not sent over plain http to an https API
+    assert_eq!(token_for("http://github.acme.com/avatars/u/1"), None);
+}
+
+
 #[tokio::test]
 async fn test_does_cache_file_exist_without_file() {
   let cache_dir = AvatarCacheDirectory::from(tempdir().unwrap().path());
@@ -80,7 +116,7 @@ async fn test_get_or_create_avatar_file_not_cached() {
     let avatar_url = Url::new(format!("{}/u/12345?v=4", &mock_server.uri()));
     let user_id = UserId::new(12345);
     let avatar_info = AvatarInfo::new(user_id, avatar_url, cache_dir.clone());
-    let file_url = get_or_create_avatar_file(&avatar_info).await.unwrap();
+    let file_url = get_or_create_avatar_file(&avatar_info, None).await.unwrap();
     let expected_file_url = format!("file://{}/{}", cache_dir, file_name);
 
     assert_eq!(file_url.to_string(), expected_file_url);
@@ -110,7 +146,7 @@ async fn test_get_or_create_avatar_file_from_cache() {
     file.write_all(data).unwrap();
 
 
-    let file_url = get_or_create_avatar_file(&avatar_info).await.unwrap();
+    let file_url = get_or_create_avatar_file(&avatar_info, None).await.unwrap();
 
     assert_eq!(file_url.to_string(), expected_file_url);
 }
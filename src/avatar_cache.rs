@@ -3,9 +3,14 @@ use std::path::PathBuf;
 use avatar::get_or_create_avatar_file;
 use model::*;
 
+// This binary only uses a subset of the shared modules
+#[allow(dead_code)]
 mod avatar;
+#[allow(dead_code)]
 mod model;
+#[allow(dead_code)]
 mod tools;
+#[allow(dead_code)]
 mod log;
 
 #[tokio::main]
@@ -21,7 +26,7 @@ async fn main() {
       avatar_url,
       AvatarCacheDirectory::new(PathBuf::from(path))
     );
-  let result = get_or_create_avatar_file(&avatar_info);
+  let result = get_or_create_avatar_file(&avatar_info, None);
 
   match result.await {
     Ok(u) => println!("got url: {:?}", u),
@@ -1,16 +1,30 @@
 use crate::model::*;
 use crate::user_dir::*;
+use crate::config_file::load_config_file;
+use crate::github_token::resolve_token;
+use crate::tools::dedup;
+use std::collections::HashMap;
 use std::ffi::OsStr;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
 
 pub fn cli() -> Result<Config, CommandLineArgumentFailure> {
+  cli_from_args(std::env::args_os())
+}
+
+/// Builds the config from the supplied command line arguments, where the first argument is the name of the program
+fn cli_from_args<I, T>(args: I) -> Result<Config, CommandLineArgumentFailure>
+  where I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone {
 
   const APPVERSION: &str = env!("CARGO_PKG_VERSION");
 
-  let working_dir_help_text = format!("Optional working directory. Defaults to USER_HOME/{}", DEFAULT_WORKING_DIR);
+  let limit_help = format!("Maximum number of matching PRs to fetch per repository. Defaults to {}. More PRs are loaded on demand when scrolling to the end of the list", DEFAULT_PR_LIMIT);
 
-  let comments_help_text = "Whether to generate comment files when there are comments. Not included by default.".to_owned();
+  let artifacts_dir_help = format!("Write the diff and comment files to a directory inside the checkout that mirrors its tree, instead of next to the changed files. The directory is relative to the checkout, such as {}. Can also be supplied through artifacts_dir in the config file. Always used with --into, where it defaults to {}", DEFAULT_ARTIFACTS_DIR, DEFAULT_ARTIFACTS_DIR);
+  let working_dir_help_text = format!("Optional working directory. Can also be supplied through the PURS_HOME environment variable. Defaults to USER_HOME/{}", DEFAULT_WORKING_DIR);
+
+  let comments_help_text = "Whether to generate comment files when there are comments. Can also be supplied through the PURS_COMMENTS environment variable. Not included by default.".to_owned();
 
   let script_help: &str =
     "Optional script to run after cloning repository\n\
@@ -18,9 +32,19 @@ pub fn cli() -> Result<Config, CommandLineArgumentFailure> {
      param1: checkout directory for the selected PR\n\
      param2: name of the file that has the names of all the changed files\n\
      \n\
+     Can also be supplied through the PURS_SCRIPT environment variable\n\
+     \n\
      Eg. purs --repo owner/repo --script path/to/your/script
     ";
 
+  let pr_help: &str =
+    "Checkout a single PR directly without displaying the list of PRs\n\
+     Supply either a PR number along with a single --repo or use the form: <owner>/<repo>#<pr_number>\n\
+     \n\
+     Eg. purs --repo owner/repo --pr 123\n\
+     Eg. purs --pr owner/repo#123
+    ";
+
   let app =
     clap::Command::new("purs")
     .version(APPVERSION)
@@ -32,14 +56,112 @@ pub fn cli() -> Result<Config, CommandLineArgumentFailure> {
             .long("repo")
             .multiple_occurrences(true)
             .takes_value(true)
-            .required(true)
-            .help("one or more GitHub repositories to include in the form: <owner>/<repo>"),
+            .env("PURS_REPOS")
+            .value_delimiter(',')
+            .global(true)
+            .help("one or more GitHub repositories to include in the form: <owner>/<repo> or as a GitHub URL. A PR URL checks out that PR directly. Can also be supplied as a comma-separated list through the PURS_REPOS environment variable"),
+    )
+    .arg(
+        clap::Arg::new("profile")
+            .long("profile")
+            .takes_value(true)
+            .env("PURS_PROFILE")
+            .global(true)
+            .help("A named group of repositories from the config file to include. Can be combined with --repo")
+    )
+    .arg(
+        clap::Arg::new("org")
+            .long("org")
+            .takes_value(true)
+            .global(true)
+            .help("Include all repositories of a GitHub organisation or user that could have PRs in the selected state. With --state open, repositories without open issues or PRs are skipped")
+    )
+    .arg(
+        clap::Arg::new("topic")
+            .long("topic")
+            .takes_value(true)
+            .requires("org")
+            .global(true)
+            .help("Only include repositories from --org that have this topic")
+    )
+    .arg(
+        clap::Arg::new("repo_glob")
+            .long("repo-glob")
+            .takes_value(true)
+            .requires("org")
+            .global(true)
+            .help("Only include repositories from --org whose name matches this glob pattern. Eg. service-*")
+    )
+    .arg(
+        clap::Arg::new("author")
+            .long("author")
+            .takes_value(true)
+            .global(true)
+            .help("Only include PRs created by this user. Use @me for yourself")
+    )
+    .arg(
+        clap::Arg::new("label")
+            .long("label")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .global(true)
+            .help("Only include PRs with this label. Can be supplied multiple times to require all labels")
+    )
+    .arg(
+        clap::Arg::new("base")
+            .long("base")
+            .takes_value(true)
+            .global(true)
+            .help("Only include PRs that target this base branch")
+    )
+    .arg(
+        clap::Arg::new("exclude_drafts")
+            .long("exclude-drafts")
+            .global(true)
+            .help("Exclude draft PRs")
+    )
+    .arg(
+        clap::Arg::new("review_requested")
+            .long("review-requested")
+            .takes_value(true)
+            .global(true)
+            .help("Only include PRs where a review has been requested from this user. Use @me for yourself")
+    )
+    .arg(
+        clap::Arg::new("state")
+            .long("state")
+            .takes_value(true)
+            .possible_values(["open", "closed", "merged", "all"])
+            .default_value("open")
+            .global(true)
+            .help("Only include PRs in this state")
+    )
+    .arg(
+        clap::Arg::new("since")
+            .long("since")
+            .takes_value(true)
+            .global(true)
+            .help("Only include PRs updated within this duration. Supports minutes (m), hours (h), days (d) and weeks (w). Eg. 12h")
+    )
+    .arg(
+        clap::Arg::new("mine")
+            .long("mine")
+            .global(true)
+            .help("Show your review inbox: open PRs across every repository you can access where your review was requested, you were mentioned, or that changed since you reviewed them. Repositories are not required")
+    )
+    .arg(
+        clap::Arg::new("limit")
+            .long("limit")
+            .takes_value(true)
+            .global(true)
+            .help(limit_help.as_str())
     )
     .arg(
         clap::Arg::new("script")
             .short('s')
             .long("script")
             .takes_value(true)
+            .env("PURS_SCRIPT")
             .next_line_help(true)
             .help(script_help)
     )
@@ -50,100 +172,636 @@ pub fn cli() -> Result<Config, CommandLineArgumentFailure> {
             .long("token")
             .env_os(OsStr::new("GH_ACCESS_TOKEN"))
             .hide_env(true)//Don't display value of GH_ACCESS_TOKEN in help text
-            .help("GitHub Access Token. Can also be supplied through the GH_ACCESS_TOKEN environment variable")
+            .global(true)
+            .help("GitHub Access Token. Can also be supplied through the GH_ACCESS_TOKEN environment variable. Otherwise the token is taken from the gh CLI, the git credential helper or the token_file in the config file")
+    )
+    .arg(
+        clap::Arg::new("clone_protocol")
+            .long("clone-protocol")
+            .takes_value(true)
+            .possible_values(["ssh", "https"])
+            .env("PURS_CLONE_PROTOCOL")
+            .global(true)
+            .help("The protocol to clone repositories with. HTTPS clones authenticate with the GitHub token. Can also be supplied through the PURS_CLONE_PROTOCOL environment variable. Defaults to ssh")
+    )
+    .arg(
+        clap::Arg::new("api_url")
+            .long("api-url")
+            .takes_value(true)
+            .env("PURS_API_URL")
+            .global(true)
+            .help("Base url of the GitHub API. Use this for GitHub Enterprise Server. Eg. https://github.acme.com/api/v3. Can also be supplied through the PURS_API_URL environment variable. Defaults to https://api.github.com")
     )
     .arg(
         clap::Arg::new("working_dir")
             .short('w')
             .long("wd")
             .takes_value(true)
+            .env("PURS_HOME")
+            .global(true)
             .help(working_dir_help_text.as_str())
     )
     .arg(
         clap::Arg::new("comments")
             .short('c')
             .long("comments")
+            .env("PURS_COMMENTS")
             .help(comments_help_text.as_str())
+    )
+    .arg(
+        clap::Arg::new("skip_access_check")
+            .long("skip-access-check")
+            .env("PURS_SKIP_ACCESS_CHECK")
+            .global(true)
+            .help("Don't check that the token and repositories can be accessed before fetching PRs. The check makes two API calls plus one per repository")
+    )
+    .arg(
+        clap::Arg::new("depth")
+            .long("depth")
+            .takes_value(true)
+            .global(true)
+            .help("Only fetch this many commits of the PR's history")
+    )
+    .arg(
+        clap::Arg::new("filter")
+            .long("filter")
+            .takes_value(true)
+            .possible_values(["blob:none"])
+            .global(true)
+            .help("Fetch the repository as a partial clone with this filter. File contents are then only downloaded when they are checked out")
+    )
+    .arg(
+        clap::Arg::new("sparse_changed_only")
+            .long("sparse-changed-only")
+            .global(true)
+            .help("Only checkout the directories with changes in the PR, along with the always_include paths from the config file")
+    )
+    .arg(
+        clap::Arg::new("diff_source")
+            .long("diff-source")
+            .takes_value(true)
+            .possible_values(["api", "local"])
+            .global(true)
+            .help("Where to generate the diff files from. api uses the diff from the GitHub API and falls back to local when the API can't provide it, such as for very large PRs. local uses git in the checkout. Defaults to api, or local when --diff-context or --ignore-whitespace is supplied")
+    )
+    .arg(
+        clap::Arg::new("diff_context")
+            .long("diff-context")
+            .takes_value(true)
+            .global(true)
+            .help("The number of lines of context around each change in local diffs")
+    )
+    .arg(
+        clap::Arg::new("ignore_whitespace")
+            .long("ignore-whitespace")
+            .global(true)
+            .help("Ignore changes in whitespace in local diffs")
+    )
+    .arg(
+        clap::Arg::new("into")
+            .long("into")
+            .takes_value(true)
+            .global(true)
+            .help("Checkout the selected PR into an existing clone of its repository, on a branch named purs/pr-<number>, instead of a new checkout under the working directory. The working tree of the clone must not have uncommitted changes")
+    )
+    .arg(
+        clap::Arg::new("artifacts_dir")
+            .long("artifacts-dir")
+            .takes_value(true)
+            .global(true)
+            .help(artifacts_dir_help.as_str())
+    )
+    .arg(
+        clap::Arg::new("force_fresh")
+            .long("force-fresh")
+            .help("Delete an existing checkout of the selected PR and clone it again. By default an existing checkout is updated to the PR's head commit")
+    )
+    .arg(
+        clap::Arg::new("pr")
+            .short('p')
+            .long("pr")
+            .takes_value(true)
+            .next_line_help(true)
+            .help(pr_help)
+    )
+    .arg(
+        clap::Arg::new("mode")
+            .short('m')
+            .long("mode")
+            .takes_value(true)
+            .possible_values(["review", "edit"])
+            .default_value("review")
+            .help("The mode to checkout a PR supplied through --pr in")
+    )
+    .subcommand(
+      clap::Command::new("list")
+        .about("List open Pull Requests without displaying the TUI")
+        .arg(
+            clap::Arg::new("format")
+                .short('f')
+                .long("format")
+                .takes_value(true)
+                .possible_values(["json", "ndjson", "table"])
+                .default_value("table")
+                .help("The format to list Pull Requests in")
+        )
     );
 
-  let matches = app.get_matches();
-
-  if let Some(repos) = matches.values_of("repo") {
-
-    let repositories_result = repos.map(|r| {
-      let mut rit = r.split('/').take(2);
-      let invalid_format_error = format!("Invalid repository format: {}", r);
-      let error = CommandLineArgumentFailure::new(&invalid_format_error);
-      let owner = rit.next().ok_or_else(|| error.clone())?;
-      let repo = rit.next().ok_or( error)?;
-
-      Ok(OwnerRepo(Owner(owner.to_owned()), Repo(repo.to_owned())))
-    }).collect::<Result<Vec<_>, CommandLineArgumentFailure>>();
-
-    let repositories_vec = repositories_result?;
-    let no_repositories_supplied_error = "No repositories supplied";
-    let repositories =
-      NonEmptyVec::from_vec(repositories_vec)
-      .ok_or_else(|| CommandLineArgumentFailure::new(no_repositories_supplied_error))?;
-
-
-    let script_option =
-      match matches.value_of("script") {
-        Some(script) => {
-          let script_path = PathBuf::from_str(script);
-          match script_path {
-            Ok(valid_script) => ScriptType::Script(ScriptToRun::new(&valid_script)),
-            Err(e) => ScriptType::InvalidScript(script.to_string(), NestedError::from(e))
-          }
-        },
-        None => ScriptType::NoScript
-      };
-
-    let script = match script_option {
-        ScriptType::NoScript => Ok(None),
-        ScriptType::Script(script_to_run) => Ok(Some(script_to_run)),
-        ScriptType::InvalidScript(script_supplied, e) => {
-          let error = format!("Invalid Script supplied: {}. Error:{}", script_supplied, e);
-          Err(CommandLineArgumentFailure::new(&error))
-        },
-    }?;
-
-    let working_dir = match matches.value_of("working_dir") {
-      Some(custom_working_dir) => WorkingDirectory::new(Path::new(custom_working_dir)),
-      None => {
-        let home_dir = get_home_dir()?;
-        let working_dir = home_dir.join(DEFAULT_WORKING_DIR);
-        WorkingDirectory::new(&working_dir)
+  let matches = app.get_matches_from(args);
+
+  let working_dir = match matches.value_of("working_dir") {
+    Some(custom_working_dir) => WorkingDirectory::new(Path::new(custom_working_dir)),
+    None => {
+      let home_dir = get_home_dir()?;
+      let working_dir = home_dir.join(DEFAULT_WORKING_DIR);
+      WorkingDirectory::new(&working_dir)
+    }
+  };
+
+  match get_or_create_working_dir(&working_dir)? {
+    WorkingDirectoryStatus::Exists => {},
+    WorkingDirectoryStatus::Created => println!("created working directory: {}", working_dir),
+  }
+  let avatar_cache_dir = working_dir.avatar_cache_dir();
+
+  let config_file = load_config_file(&working_dir.config_file())?;
+
+  let api_url =
+    matches
+      .value_of("api_url")
+      .or(config_file.api_url.as_deref())
+      .map(parse_api_url)
+      .transpose()?;
+
+  // Repository and PR URLs must be on the host the API belongs to
+  let github_host = api_url.as_ref().map(|u| u.host()).unwrap_or(DEFAULT_GITHUB_HOST);
+
+  let inbox = matches.is_present("mine");
+  let is_from_command_line = |id: &str| matches.value_source(id) == Some(clap::ValueSource::CommandLine);
+
+  // The inbox searches every repository, so repositories and profiles from the environment are ignored instead of rejected
+  let repository_arguments =
+    matches
+      .values_of("repo")
+      .filter(|_| !inbox || is_from_command_line("repo"))
+      .map(|repos| repos.map(|r| parse_repository_argument(r, github_host)).collect::<Result<Vec<_>, CommandLineArgumentFailure>>())
+      .transpose()?;
+
+  // PR URLs supplied through --repo imply a direct checkout of that PR
+  let (cli_repositories, url_prs) =
+    match repository_arguments {
+      Some(arguments) => {
+        let mut repos = vec![];
+        let mut prs = vec![];
+        arguments.into_iter().for_each(|argument| match argument {
+          RepositoryArgument::Repository(owner_repo) => repos.push(owner_repo),
+          RepositoryArgument::PullRequest(direct_pr) => prs.push(direct_pr),
+        });
+        // Only PR URLs were supplied, so there are no repositories to list
+        let repos_option = if repos.is_empty() { None } else { Some(repos) };
+        (repos_option, prs)
+      },
+      None => (None, vec![])
+    };
+
+  let profile = matches.value_of("profile").filter(|_| !inbox || is_from_command_line("profile")).map(ProfileName::new);
+
+  let repositories_vec =
+    match (&profile, cli_repositories) {
+      // Repositories from a profile are combined with any from the command line or environment
+      (Some(profile_name), cli_repos) => {
+        let profile_repos = get_profile_repositories(&config_file, profile_name, github_host)?;
+        dedup(profile_repos.into_iter().chain(cli_repos.into_iter().flatten()).collect())
+      },
+      // Repositories from the command line or environment replace those in the config file
+      (None, Some(cli_repos)) => cli_repos,
+      (None, None) => {
+        config_file
+          .repos
+          .iter()
+          .flatten()
+          .map(|r| parse_owner_repo(r, github_host))
+          .collect::<Result<Vec<_>, CommandLineArgumentFailure>>()?
       }
     };
 
-    match get_or_create_working_dir(&working_dir)? {
-      WorkingDirectoryStatus::Exists => {},
-      WorkingDirectoryStatus::Created => println!("created working directory: {}", working_dir),
+  let direct_pr =
+    match (matches.value_of("pr"), url_prs.as_slice()) {
+      (None, []) => None,
+      (Some(pr), []) => Some(parse_direct_pr(pr, &repositories_vec, github_host)?),
+      (None, [url_pr]) => Some(url_pr.clone()),
+      (None, _) => return Err(CommandLineArgumentFailure::new("Only one PR URL can be supplied")),
+      (Some(_), _) => return Err(CommandLineArgumentFailure::new("A PR URL can't be supplied along with --pr")),
+    };
+
+  // A PR supplied as owner/repo#number is enough to determine the repository
+  let repositories_with_pr =
+    match (&direct_pr, repositories_vec.is_empty()) {
+      (Some(pr), true) => vec![pr.owner_repo.clone()],
+      _ => repositories_vec
+    };
+
+  let org =
+    matches
+      .value_of("org")
+      .map(|owner| {
+        OrgFilter {
+          owner: Owner(owner.to_owned()),
+          topic: matches.value_of("topic").map(|t| t.to_owned()),
+          name_glob: matches.value_of("repo_glob").map(|g| g.to_owned()),
+        }
+      });
+
+  // The inbox searches every repository, so it can't be limited to some repositories
+  if inbox && (is_from_command_line("repo") || profile.is_some() || direct_pr.is_some() || org.is_some()) {
+    return Err(CommandLineArgumentFailure::new("--mine can't be used with --repo, --profile, --pr or --org"))
+  }
+
+  // The inbox only lists open PRs
+  if inbox && matches.value_of("state").and_then(PrStateFilter::from_short_string).unwrap_or_default() != PrStateFilter::Open {
+    return Err(CommandLineArgumentFailure::new("--mine only lists open PRs, so it can't be used with --state closed, merged or all"))
+  }
+
+  // Repositories of an org are only known once we query GitHub and the inbox searches every repository
+  let no_repositories_supplied_error = "Invalid command line argument combination, expected at least one repository, an org or --mine.";
+  if repositories_with_pr.is_empty() && org.is_none() && !inbox {
+    return Err(CommandLineArgumentFailure::new(no_repositories_supplied_error))
+  }
+  let repositories = repositories_with_pr;
+
+  let filters =
+    PrFilters {
+      author: matches.value_of("author").map(UserFilter::new),
+      labels: matches.values_of("label").map(|labels| labels.map(|l| l.to_owned()).collect()).unwrap_or_default(),
+      base: matches.value_of("base").map(|b| b.to_owned()),
+      exclude_drafts: matches.is_present("exclude_drafts"),
+      review_requested: matches.value_of("review_requested").map(UserFilter::new),
+      state: matches.value_of("state").and_then(PrStateFilter::from_short_string).unwrap_or_default(),
+      since: matches.value_of("since").map(parse_duration).transpose()?,
+    };
+
+  let limit = matches.value_of("limit").map(parse_limit).transpose()?.unwrap_or_default();
+
+  let cli_script = matches.value_of("script").map(parse_script).transpose()?;
+  let cli_comments = matches.is_present("comments");
+
+  let script =
+    match &cli_script {
+      Some(script) => Some(script.clone()),
+      None => config_file.script.as_deref().map(parse_script).transpose()?
+    };
+
+
+  let include_comments = cli_comments || config_file.comments.unwrap_or(false);
+
+  let skip_access_check = matches.is_present("skip_access_check") || config_file.skip_access_check.unwrap_or(false);
+
+  let cli_clone_protocol = matches.value_of("clone_protocol").and_then(CloneProtocol::from_short_string);
+
+  let clone_protocol =
+    cli_clone_protocol
+      .clone()
+      .or_else(|| config_file.clone_protocol.clone())
+      .unwrap_or_default();
+
+  let clone_options =
+    CloneOptions {
+      depth: matches.value_of("depth").map(parse_depth).transpose()?,
+      filter: matches.value_of("filter").and_then(CloneFilter::from_short_string),
+      sparse_changed_only: matches.is_present("sparse_changed_only")
+    };
+
+  let always_include = config_file.always_include.clone().unwrap_or_default();
+
+  let diff_options = parse_diff_options(matches.value_of("diff_source"), matches.value_of("diff_context"), matches.is_present("ignore_whitespace"))?;
+
+  let token_file = config_file.token_file.as_deref().map(expand_home_dir).transpose()?;
+  let (token, token_source) = resolve_token(matches.value_of("gh_token"), github_host, token_file.as_deref())?;
+
+  // Use stderr so the output of the list command can be piped
+  eprintln!("Using GitHub token from: {}", token_source);
+
+  let repository_overrides =
+    config_file
+      .repositories
+      .iter()
+      .map(|(owner_repo, override_toml)| {
+        let owner_repo = parse_owner_repo(owner_repo, github_host)?;
+        // Settings from the command line or environment take precedence over the config file
+        let script =
+          match cli_script {
+            Some(_) => None,
+            None => override_toml.script.as_deref().map(parse_script).transpose()?
+          };
+        let include_comments = if cli_comments { None } else { override_toml.comments };
+
+        let repository_override =
+          RepositoryOverride {
+            script,
+            include_comments,
+            clone_protocol: if cli_clone_protocol.is_some() { None } else { override_toml.clone_protocol.clone() },
+            always_include: override_toml.always_include.clone()
+          };
+
+        Ok((owner_repo, repository_override))
+      })
+      .collect::<Result<HashMap<_, _>, CommandLineArgumentFailure>>()?;
+
+  let mode =
+    matches
+      .value_of("mode")
+      .and_then(Mode::from_short_string)
+      .unwrap_or(Mode::Review);
+
+  let command =
+    match matches.subcommand_matches("list") {
+      Some(list_matches) => {
+        let format =
+          list_matches
+            .value_of("format")
+            .and_then(ListFormat::from_short_string)
+            .unwrap_or(ListFormat::Table);
+
+        PursCommand::List(format)
+      },
+      None => PursCommand::Checkout
+    };
+
+  if let (PursCommand::List(_), Some(_)) = (&command, &direct_pr) {
+    return Err(CommandLineArgumentFailure::new("--pr can't be used with the list command"))
+  }
+
+  let force_fresh = matches.is_present("force_fresh");
+
+  let into =
+    matches
+      .value_of("into")
+      .map(|path| Ok(LocalRepository { path: expand_home_dir(path)? }))
+      .transpose()?;
+
+  // The diff and comment files are never written next to the sources of an existing clone
+  let artifacts_dir =
+    matches
+      .value_of("artifacts_dir")
+      .or(config_file.artifacts_dir.as_deref())
+      .or_else(|| into.as_ref().map(|_| DEFAULT_ARTIFACTS_DIR))
+      .map(parse_artifacts_dir)
+      .transpose()?;
+
+  if let (PursCommand::List(_), Some(_)) = (&command, &into) {
+    return Err(CommandLineArgumentFailure::new("--into can't be used with the list command"))
+  }
+
+  let config =
+    Config {
+      working_dir,
+      avatar_cache_dir,
+      repositories,
+      org,
+      profile,
+      filters,
+      limit,
+      inbox,
+      api_url,
+      token,
+      script,
+      include_comments,
+      skip_access_check,
+      clone_protocol,
+      clone_options,
+      diff_options,
+      always_include,
+      force_fresh,
+      into,
+      artifacts_dir,
+      repository_overrides,
+      direct_pr,
+      mode,
+      command
+    };
+
+  Ok(config)
+}
+
+pub fn get_profile_repositories(config_file: &ConfigFileToml, profile: &ProfileName, github_host: &str) -> Result<Vec<OwnerRepo>, CommandLineArgumentFailure> {
+  match config_file.profiles.get(&profile.to_string()) {
+    Some(repos) => repos.iter().map(|r| parse_owner_repo(r, github_host)).collect(),
+    None => {
+      let mut available_profiles = config_file.profiles.keys().cloned().collect::<Vec<_>>();
+      available_profiles.sort();
+      let error = format!("Unknown profile: {}. Available profiles: [{}]", profile, available_profiles.join(", "));
+      Err(CommandLineArgumentFailure::new(&error))
+    }
+  }
+}
+
+pub fn parse_script(script: &str) -> Result<ScriptToRun, CommandLineArgumentFailure> {
+  PathBuf::from_str(script)
+    .map(|valid_script| ScriptToRun::new(&valid_script))
+    .map_err(|e| {
+      let error = format!("Invalid Script supplied: {}. Error:{}", script, NestedError::from(e));
+      CommandLineArgumentFailure::new(&error)
+    })
+}
+
+pub fn parse_owner_repo(owner_repo: &str, github_host: &str) -> Result<OwnerRepo, CommandLineArgumentFailure> {
+  match parse_repository_argument(owner_repo, github_host)? {
+    RepositoryArgument::Repository(owner_repo) => Ok(owner_repo),
+    RepositoryArgument::PullRequest(_) => {
+      let error = format!("Expected a repository but got a PR: {}", owner_repo);
+      Err(CommandLineArgumentFailure::new(&error))
     }
-    let avatar_cache_dir = working_dir.avatar_cache_dir();
+  }
+}
 
-    let token =
-      matches
-      .value_of("gh_token")
-      .ok_or_else( || CommandLineArgumentFailure::new("Could not find Github Personal Access Token"))
-      .map(GitHubToken::new)?;
+/// Parses a repository supplied as any of:
+///   <owner>/<repo>
+///   https://github.com/<owner>/<repo>
+///   git@github.com:<owner>/<repo>.git
+///   https://github.com/<owner>/<repo>/pull/<pr_number>
+/// URLs must be on the github_host, which is github.com or the host of the GitHub Enterprise Server API.
+pub fn parse_repository_argument(argument: &str, github_host: &str) -> Result<RepositoryArgument, CommandLineArgumentFailure> {
+  let error = |reason: &str| CommandLineArgumentFailure::new(&format!("Invalid repository: {}. {}", argument, reason));
 
+  let (host, path) =
+    if let Some(url) = argument.strip_prefix("https://").or_else(|| argument.strip_prefix("http://")).or_else(|| argument.strip_prefix("ssh://git@")) {
+      let (host, path) = url.split_once('/').ok_or_else(|| error("Expected a URL of the form: https://<host>/<owner>/<repo>"))?;
+      // Remove any query string or fragment
+      let path = path.split(['?', '#']).next().unwrap_or_default();
+      (Some(host), path)
+    } else if let Some(url) = argument.strip_prefix("git@") {
+      let (host, path) = url.split_once(':').ok_or_else(|| error("Expected a URL of the form: git@<host>:<owner>/<repo>.git"))?;
+      (Some(host), path)
+    } else {
+      (None, argument)
+    };
 
-    let include_comments = matches.is_present("comments");
+  let is_url = host.is_some();
+  if let Some(host) = host {
+    // Remove any user information and port
+    let host_name = host.rsplit('@').next().unwrap_or_default().split(':').next().unwrap_or_default();
+    if !host_name.eq_ignore_ascii_case(github_host) {
+      return Err(error(&format!("Unsupported host: {}. Expected a URL on {}", host_name, github_host)))
+    }
+  }
 
-    let config =
-      Config {
-        working_dir,
-        avatar_cache_dir,
-        repositories,
-        token,
-        script,
-        include_comments
-      };
+  let path = if is_url { path.trim_end_matches('/') } else { path };
+  let segments = path.split('/').collect::<Vec<_>>();
 
-    Ok(config)
+  match segments.as_slice() {
+    [owner, repo] => {
+      let repo = if is_url { repo.trim_end_matches(".git") } else { repo };
+      let owner_repo = validate_owner_repo(owner, repo).map_err(|reason| error(&reason))?;
+      Ok(RepositoryArgument::Repository(owner_repo))
+    },
+    // PR URLs may point to a tab of the PR such as /files or /commits
+    [owner, repo, "pull", pr_number, ..] if is_url => {
+      let owner_repo = validate_owner_repo(owner, repo).map_err(|reason| error(&reason))?;
+      let pr_number = pr_number.parse::<u64>().map_err(|_| error(&format!("Invalid PR number: {}", pr_number)))?;
+      Ok(RepositoryArgument::PullRequest(DirectPr { owner_repo, pr_number }))
+    },
+    [_] => Err(error("Expected the form: <owner>/<repo>")),
+    _ => Err(error("Too many path segments. Expected the form: <owner>/<repo>")),
+  }
+}
+
+fn validate_owner_repo(owner: &str, repo: &str) -> Result<OwnerRepo, String> {
+  // GitHub Enterprise Server allows underscores in owner names
+  let is_valid_owner = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+  let is_valid_repo = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+
+  if owner.is_empty() {
+    Err("Missing owner".to_owned())
+  } else if repo.is_empty() {
+    Err("Missing repository name".to_owned())
+  } else if !owner.chars().all(is_valid_owner) {
+    Err(format!("Invalid owner: {}", owner))
+  } else if !repo.chars().all(is_valid_repo) || repo == "." || repo == ".." {
+    Err(format!("Invalid repository name: {}", repo))
   } else {
-    Err(CommandLineArgumentFailure::new("Invalid command line argument combination, expected at least one repository."))
+    Ok(OwnerRepo(Owner(owner.to_owned()), Repo(repo.to_owned())))
   }
 }
+
+/// Parses a duration such as 30m, 12h, 2d or 1w
+pub fn parse_duration(duration: &str) -> Result<chrono::Duration, CommandLineArgumentFailure> {
+  let error = || CommandLineArgumentFailure::new(&format!("Invalid duration: {}. Expected a number followed by m, h, d or w. Eg. 12h", duration));
+
+  let unit_index = duration.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+  let (amount, unit) = duration.split_at(unit_index);
+  let amount = amount.parse::<i64>().map_err(|_| error())?;
+
+  match unit {
+    "m" => Ok(chrono::Duration::minutes(amount)),
+    "h" => Ok(chrono::Duration::hours(amount)),
+    "d" => Ok(chrono::Duration::days(amount)),
+    "w" => Ok(chrono::Duration::weeks(amount)),
+    _ => Err(error())
+  }
+}
+
+/// Replaces a leading ~ with the home directory
+pub fn expand_home_dir(path: &str) -> Result<PathBuf, CommandLineArgumentFailure> {
+  match path.strip_prefix("~/") {
+    Some(relative_path) => Ok(get_home_dir()?.join(relative_path)),
+    None => Ok(PathBuf::from(path))
+  }
+}
+
+pub fn parse_api_url(api_url: &str) -> Result<ApiUrl, CommandLineArgumentFailure> {
+  ApiUrl::new(api_url)
+    .ok_or_else(|| CommandLineArgumentFailure::new(&format!("Invalid API url: {}. Expected an http or https url. Eg. https://github.acme.com/api/v3", api_url)))
+}
+
+pub fn parse_limit(limit: &str) -> Result<PrLimit, CommandLineArgumentFailure> {
+  limit
+    .parse::<usize>()
+    .ok()
+    .and_then(PrLimit::new)
+    .ok_or_else(|| CommandLineArgumentFailure::new(&format!("Invalid limit: {}. Expected a number greater than zero", limit)))
+}
+
+/// The files of the previous run are removed from the artifacts directory on each checkout, so it must be a directory inside the checkout
+pub fn parse_artifacts_dir(artifacts_dir: &str) -> Result<PathBuf, CommandLineArgumentFailure> {
+  let path = PathBuf::from(artifacts_dir);
+  let is_inside_checkout = path.components().all(|component| matches!(component, Component::Normal(name) if name != ".git"));
+
+  if path.as_os_str().is_empty() || !is_inside_checkout {
+    Err(CommandLineArgumentFailure::new(&format!("Invalid artifacts directory: {}. Expected a relative path inside the checkout that is not .git", artifacts_dir)))
+  } else {
+    Ok(path)
+  }
+}
+
+/// Context lines and ignoring whitespace can only be applied to local diffs, so they select local diffs unless the API was asked for
+pub fn parse_diff_options(source: Option<&str>, context_lines: Option<&str>, ignore_whitespace: bool) -> Result<DiffOptions, CommandLineArgumentFailure> {
+  let context_lines =
+    context_lines
+      .map(|lines| {
+        lines
+          .parse::<usize>()
+          .map_err(|_| CommandLineArgumentFailure::new(&format!("Invalid diff context: {}. Expected a number of lines", lines)))
+      })
+      .transpose()?;
+
+  let has_local_options = context_lines.is_some() || ignore_whitespace;
+
+  let source =
+    match source.and_then(DiffSource::from_short_string) {
+      Some(DiffSource::Api) if has_local_options =>
+        return Err(CommandLineArgumentFailure::new("--diff-context and --ignore-whitespace can only be used with --diff-source local")),
+      Some(source) => source,
+      None if has_local_options => DiffSource::Local,
+      None => DiffSource::Api
+    };
+
+  Ok(
+    DiffOptions {
+      source,
+      context_lines,
+      ignore_whitespace
+    }
+  )
+}
+
+pub fn parse_depth(depth: &str) -> Result<CloneDepth, CommandLineArgumentFailure> {
+  depth
+    .parse::<usize>()
+    .ok()
+    .and_then(CloneDepth::new)
+    .ok_or_else(|| CommandLineArgumentFailure::new(&format!("Invalid depth: {}. Expected a number greater than zero", depth)))
+}
+
+/// Parses a PR supplied as either a bare PR number (which requires exactly one repository), as <owner>/<repo>#<pr_number> or as a PR URL
+pub fn parse_direct_pr(pr: &str, repositories: &[OwnerRepo], github_host: &str) -> Result<DirectPr, CommandLineArgumentFailure> {
+  let invalid_pr_number = |number: &str| CommandLineArgumentFailure::new(&format!("Invalid PR number: {}", number));
+
+  if pr.contains("://") || pr.starts_with("git@") {
+    return match parse_repository_argument(pr, github_host)? {
+      RepositoryArgument::PullRequest(direct_pr) => Ok(direct_pr),
+      RepositoryArgument::Repository(_) => Err(CommandLineArgumentFailure::new(&format!("Expected a PR URL but got a repository: {}", pr)))
+    }
+  }
+
+  match pr.split_once('#') {
+    Some((owner_repo, number)) => {
+      let owner_repo = parse_owner_repo(owner_repo, github_host)?;
+      let pr_number = number.parse::<u64>().map_err(|_| invalid_pr_number(number))?;
+
+      Ok(DirectPr { owner_repo, pr_number })
+    },
+    None => {
+      let pr_number = pr.parse::<u64>().map_err(|_| invalid_pr_number(pr))?;
+      match repositories {
+        [owner_repo] => Ok(DirectPr { owner_repo: owner_repo.clone(), pr_number }),
+        [] => Err(CommandLineArgumentFailure::new(&format!("PR number {} supplied without a repository. Use --repo <owner>/<repo> or --pr <owner>/<repo>#{}", pr, pr))),
+        _ => Err(CommandLineArgumentFailure::new(&format!("PR number {} is ambiguous across multiple repositories. Use --pr <owner>/<repo>#{}", pr, pr))),
+      }
+    }
+  }
+}
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
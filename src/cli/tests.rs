@@ -0,0 +1,247 @@
+use crate::cli::{cli_from_args, parse_owner_repo, parse_direct_pr, get_profile_repositories, parse_repository_argument, parse_duration, parse_limit, parse_depth, parse_api_url, parse_artifacts_dir, parse_diff_options};
+use std::path::PathBuf;
+use std::collections::HashMap;
+use crate::model::*;
+use pretty_assertions::assert_eq;
+use tempfile::tempdir;
+
+fn owner_repo(owner: &str, repo: &str) -> OwnerRepo {
+  OwnerRepo(Owner(owner.to_owned()), Repo(repo.to_owned()))
+}
+
+#[test]
+fn parses_owner_repo() {
+  let result = parse_owner_repo("ssanj/purs", DEFAULT_GITHUB_HOST);
+  assert_eq!(result, Ok(owner_repo("ssanj", "purs")))
+}
+
+#[test]
+fn parses_direct_pr_with_owner_repo() {
+  let result = parse_direct_pr("ssanj/purs#42", &[], DEFAULT_GITHUB_HOST);
+  assert_eq!(result, Ok(DirectPr { owner_repo: owner_repo("ssanj", "purs"), pr_number: 42 }))
+}
+
+#[test]
+fn parses_direct_pr_number_with_single_repository() {
+  let result = parse_direct_pr("42", &[owner_repo("ssanj", "purs")], DEFAULT_GITHUB_HOST);
+  assert_eq!(result, Ok(DirectPr { owner_repo: owner_repo("ssanj", "purs"), pr_number: 42 }))
+}
+
+#[test]
+fn rejects_direct_pr_number_without_repository() {
+  let result = parse_direct_pr("42", &[], DEFAULT_GITHUB_HOST);
+  assert!(result.is_err())
+}
+
+#[test]
+fn rejects_ambiguous_direct_pr_number() {
+  let result = parse_direct_pr("42", &[owner_repo("ssanj", "purs"), owner_repo("ssanj", "giter8")], DEFAULT_GITHUB_HOST);
+  assert!(result.is_err())
+}
+
+#[test]
+fn rejects_invalid_direct_pr_number() {
+  let result = parse_direct_pr("ssanj/purs#abc", &[], DEFAULT_GITHUB_HOST);
+  assert_eq!(result, Err(CommandLineArgumentFailure::new("Invalid PR number: abc")))
+}
+
+#[test]
+fn gets_repositories_for_profile() {
+  let config_file =
+    ConfigFileToml {
+      profiles: HashMap::from([("backend".to_owned(), vec!["ssanj/purs".to_owned(), "ssanj/giter8".to_owned()])]),
+      ..ConfigFileToml::default()
+    };
+
+  let result = get_profile_repositories(&config_file, &ProfileName::new("backend"), DEFAULT_GITHUB_HOST);
+  assert_eq!(result, Ok(vec![owner_repo("ssanj", "purs"), owner_repo("ssanj", "giter8")]))
+}
+
+#[test]
+fn rejects_unknown_profile() {
+  let config_file =
+    ConfigFileToml {
+      profiles: HashMap::from([("backend".to_owned(), vec!["ssanj/purs".to_owned()])]),
+      ..ConfigFileToml::default()
+    };
+
+  let result = get_profile_repositories(&config_file, &ProfileName::new("mobile"), DEFAULT_GITHUB_HOST);
+  assert_eq!(result, Err(CommandLineArgumentFailure::new("Unknown profile: mobile. Available profiles: [backend]")))
+}
+
+#[test]
+fn parses_repository_urls() {
+  let expected = Ok(RepositoryArgument::Repository(owner_repo("ssanj", "purs")));
+
+  assert_eq!(parse_repository_argument("https://github.com/ssanj/purs", DEFAULT_GITHUB_HOST), expected);
+  assert_eq!(parse_repository_argument("https://github.com/ssanj/purs/", DEFAULT_GITHUB_HOST), expected);
+  assert_eq!(parse_repository_argument("https://github.com/ssanj/purs.git", DEFAULT_GITHUB_HOST), expected);
+  assert_eq!(parse_repository_argument("git@github.com:ssanj/purs.git", DEFAULT_GITHUB_HOST), expected);
+  assert_eq!(parse_repository_argument("ssh://git@github.com/ssanj/purs.git", DEFAULT_GITHUB_HOST), expected);
+}
+
+#[test]
+fn parses_pr_urls() {
+  let expected = Ok(RepositoryArgument::PullRequest(DirectPr { owner_repo: owner_repo("ssanj", "purs"), pr_number: 42 }));
+
+  assert_eq!(parse_repository_argument("https://github.com/ssanj/purs/pull/42", DEFAULT_GITHUB_HOST), expected);
+  assert_eq!(parse_repository_argument("https://github.com/ssanj/purs/pull/42/files", DEFAULT_GITHUB_HOST), expected);
+  assert_eq!(parse_repository_argument("https://github.com/ssanj/purs/pull/42#discussion_r820688697", DEFAULT_GITHUB_HOST), expected);
+  assert_eq!(parse_direct_pr("https://github.com/ssanj/purs/pull/42", &[], DEFAULT_GITHUB_HOST), Ok(DirectPr { owner_repo: owner_repo("ssanj", "purs"), pr_number: 42 }));
+}
+
+#[test]
+fn rejects_malformed_repositories() {
+  assert_eq!(parse_owner_repo("ssanj/purs/extra", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: ssanj/purs/extra. Too many path segments. Expected the form: <owner>/<repo>")));
+  assert_eq!(parse_owner_repo("ssanj/", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: ssanj/. Missing repository name")));
+  assert_eq!(parse_owner_repo("/purs", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: /purs. Missing owner")));
+  assert_eq!(parse_owner_repo("ssanj", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: ssanj. Expected the form: <owner>/<repo>")));
+  assert_eq!(parse_owner_repo("ss anj/purs", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: ss anj/purs. Invalid owner: ss anj")));
+  assert_eq!(parse_owner_repo("ssanj/..", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: ssanj/... Invalid repository name: ..")));
+  assert_eq!(parse_owner_repo("https://github.com/ssanj/purs/pull/abc", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: https://github.com/ssanj/purs/pull/abc. Invalid PR number: abc")));
+}
+
+#[test]
+fn rejects_pr_url_as_repository() {
+  let result = parse_owner_repo("https://github.com/ssanj/purs/pull/42", DEFAULT_GITHUB_HOST);
+  assert_eq!(result, Err(CommandLineArgumentFailure::new("Expected a repository but got a PR: https://github.com/ssanj/purs/pull/42")))
+}
+
+#[test]
+fn rejects_urls_on_other_hosts() {
+  assert_eq!(parse_owner_repo("https://gitlab.com/ssanj/purs", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: https://gitlab.com/ssanj/purs. Unsupported host: gitlab.com. Expected a URL on github.com")));
+  assert_eq!(parse_owner_repo("git@github.acme.com:ssanj/purs.git", DEFAULT_GITHUB_HOST), Err(CommandLineArgumentFailure::new("Invalid repository: git@github.acme.com:ssanj/purs.git. Unsupported host: github.acme.com. Expected a URL on github.com")));
+}
+
+#[test]
+fn parses_urls_on_the_enterprise_host() {
+  let expected = Ok(RepositoryArgument::Repository(owner_repo("acme_platform", "purs")));
+
+  assert_eq!(parse_repository_argument("https://github.acme.com/acme_platform/purs", "github.acme.com"), expected);
+  assert_eq!(parse_repository_argument("https://GitHub.acme.com:8443/acme_platform/purs", "github.acme.com"), expected);
+  assert_eq!(parse_repository_argument("git@github.acme.com:acme_platform/purs.git", "github.acme.com"), expected);
+  assert!(parse_repository_argument("https://github.com/acme_platform/purs", "github.acme.com").is_err());
+}
+
+#[test]
+fn parses_durations() {
+  assert_eq!(parse_duration("30m"), Ok(chrono::Duration::minutes(30)));
+  assert_eq!(parse_duration("12h"), Ok(chrono::Duration::hours(12)));
+  assert_eq!(parse_duration("2d"), Ok(chrono::Duration::days(2)));
+  assert_eq!(parse_duration("1w"), Ok(chrono::Duration::weeks(1)));
+}
+
+#[test]
+fn rejects_invalid_durations() {
+  assert!(parse_duration("12").is_err());
+  assert!(parse_duration("h").is_err());
+  assert!(parse_duration("12y").is_err());
+  assert!(parse_duration("1.5h").is_err());
+}
+
+#[test]
+fn parses_limit() {
+  assert_eq!(parse_limit("50"), Ok(PrLimit::new(50).unwrap()));
+}
+
+#[test]
+fn rejects_invalid_limits() {
+  assert!(parse_limit("0").is_err());
+  assert!(parse_limit("-1").is_err());
+  assert!(parse_limit("ten").is_err());
+}
+
+#[test]
+fn parses_depth() {
+  assert_eq!(parse_depth("1"), Ok(CloneDepth::new(1).unwrap()));
+  assert!(parse_depth("0").is_err());
+  assert!(parse_depth("deep").is_err());
+}
+
+#[test]
+fn parses_artifacts_dir() {
+  assert_eq!(parse_artifacts_dir(".purs"), Ok(PathBuf::from(".purs")));
+  assert_eq!(parse_artifacts_dir("review/files"), Ok(PathBuf::from("review/files")));
+}
+
+#[test]
+fn rejects_artifacts_dir_outside_checkout() {
+  assert!(parse_artifacts_dir("").is_err());
+  assert!(parse_artifacts_dir("/tmp/purs").is_err());
+  assert!(parse_artifacts_dir("../purs").is_err());
+  assert!(parse_artifacts_dir(".").is_err());
+  assert!(parse_artifacts_dir(".git").is_err());
+  assert!(parse_artifacts_dir(".git/purs").is_err());
+}
+
+#[test]
+fn parses_diff_options() {
+  assert_eq!(parse_diff_options(None, None, false), Ok(DiffOptions::default()));
+  assert_eq!(
+    parse_diff_options(Some("local"), None, false),
+    Ok(DiffOptions { source: DiffSource::Local, context_lines: None, ignore_whitespace: false })
+  );
+}
+
+#[test]
+fn selects_local_diffs_with_local_diff_options() {
+  assert_eq!(
+    parse_diff_options(None, Some("10"), true),
+    Ok(DiffOptions { source: DiffSource::Local, context_lines: Some(10), ignore_whitespace: true })
+  );
+}
+
+#[test]
+fn rejects_local_diff_options_with_api_diffs() {
+  assert!(parse_diff_options(Some("api"), Some("10"), false).is_err());
+  assert!(parse_diff_options(Some("api"), None, true).is_err());
+  assert!(parse_diff_options(None, Some("many"), false).is_err());
+}
+
+#[test]
+fn parses_api_url() {
+  let api_url = parse_api_url("https://github.acme.com/api/v3").unwrap();
+  assert_eq!(api_url.host(), "github.acme.com");
+}
+
+#[test]
+fn rejects_invalid_api_urls() {
+  assert!(parse_api_url("github.acme.com/api/v3").is_err());
+  assert!(parse_api_url("ftp://github.acme.com").is_err());
+}
+
+#[test]
+fn ignores_repositories_and_profile_from_environment_with_mine() {
+  let working_dir = tempdir().unwrap();
+  let working_dir_arg = working_dir.path().to_string_lossy().to_string();
+
+  std::env::set_var("PURS_REPOS", "ssanj/purs");
+  std::env::set_var("PURS_PROFILE", "backend");
+
+  let inbox_result = cli_from_args(["purs", "--mine", "-t", "token", "--wd", &working_dir_arg]);
+  let repo_result = cli_from_args(["purs", "--mine", "--repo", "ssanj/giter8", "-t", "token", "--wd", &working_dir_arg]);
+
+  std::env::remove_var("PURS_REPOS");
+  std::env::remove_var("PURS_PROFILE");
+
+  let config = inbox_result.unwrap();
+  assert!(config.inbox);
+  assert!(config.repositories.is_empty());
+  assert!(config.profile.is_none());
+
+  assert_eq!(repo_result.unwrap_err(), CommandLineArgumentFailure::new("--mine can't be used with --repo, --profile, --pr or --org"));
+}
+
+#[test]
+fn rejects_mine_with_states_other_than_open() {
+  let working_dir = tempdir().unwrap();
+  let working_dir_arg = working_dir.path().to_string_lossy().to_string();
+
+  let open_result = cli_from_args(["purs", "--mine", "--state", "open", "-t", "token", "--wd", &working_dir_arg]);
+  assert!(open_result.is_ok());
+
+  for state in ["closed", "merged", "all"] {
+    let result = cli_from_args(["purs", "--mine", "--state", state, "-t", "token", "--wd", &working_dir_arg]);
+    assert_eq!(result.unwrap_err(), CommandLineArgumentFailure::new("--mine only lists open PRs, so it can't be used with --state closed, merged or all"));
+  }
+}
@@ -0,0 +1,25 @@
+use crate::model::*;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Loads the config file from the working directory. A missing config file is the same as an empty one.
+pub fn load_config_file(config_file: &Path) -> Result<ConfigFileToml, CommandLineArgumentFailure> {
+  match fs::read_to_string(config_file) {
+    Ok(contents) => parse_config_file(&contents).map_err(|e| {
+      CommandLineArgumentFailure::new(&format!("Invalid config file: {}. Error: {}", config_file.to_string_lossy(), e))
+    }),
+    Err(e) if e.kind() == ErrorKind::NotFound => Ok(ConfigFileToml::default()),
+    Err(e) => Err(CommandLineArgumentFailure::new(&format!("Could not read config file: {}. Error: {}", config_file.to_string_lossy(), e)))
+  }
+}
+
+pub fn parse_config_file(contents: &str) -> Result<ConfigFileToml, CommandLineArgumentFailure> {
+  toml::from_str(contents)
+    .map_err(|e| CommandLineArgumentFailure::new(&e.to_string()))
+}
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,81 @@
+use crate::config_file::{load_config_file, parse_config_file};
+use crate::model::*;
+use pretty_assertions::assert_eq;
+use std::collections::HashMap;
+use tempfile::tempdir;
+
+#[test]
+fn parses_config_file_with_repository_overrides() {
+  let contents = r#"
+    repos = ["owner/repo1", "owner/repo2"]
+    script = "/path/to/script"
+    comments = true
+    skip_access_check = true
+    api_url = "https://github.acme.com/api/v3"
+    token_file = "/path/to/token"
+    always_include = ["build", "project"]
+    artifacts_dir = ".review"
+
+    [repositories."owner/repo1"]
+    script = "/path/to/other/script"
+    comments = false
+    clone_protocol = "https"
+    always_include = ["tools"]
+
+    [profiles]
+    backend = ["owner/api", "owner/worker"]
+  "#;
+
+  let result = parse_config_file(contents).unwrap();
+
+  let expected =
+    ConfigFileToml {
+      repos: Some(vec!["owner/repo1".to_owned(), "owner/repo2".to_owned()]),
+      script: Some("/path/to/script".to_owned()),
+      comments: Some(true),
+      skip_access_check: Some(true),
+      clone_protocol: None,
+      api_url: Some("https://github.acme.com/api/v3".to_owned()),
+      token_file: Some("/path/to/token".to_owned()),
+      always_include: Some(vec!["build".to_owned(), "project".to_owned()]),
+      artifacts_dir: Some(".review".to_owned()),
+      repositories:
+        HashMap::from([
+          (
+            "owner/repo1".to_owned(),
+            RepositoryOverrideToml {
+              script: Some("/path/to/other/script".to_owned()),
+              comments: Some(false),
+              clone_protocol: Some(CloneProtocol::Https),
+              always_include: Some(vec!["tools".to_owned()])
+            }
+          )
+        ]),
+      profiles:
+        HashMap::from([
+          ("backend".to_owned(), vec!["owner/api".to_owned(), "owner/worker".to_owned()])
+        ])
+    };
+
+  assert_eq!(result, expected)
+}
+
+#[test]
+fn parses_artifacts_dir() {
+  let result = parse_config_file("artifacts_dir = \".purs\"").unwrap();
+  assert_eq!(result.artifacts_dir, Some(".purs".to_owned()))
+}
+
+#[test]
+fn rejects_unknown_config_keys() {
+  let result = parse_config_file("repo = \"owner/repo1\"");
+  assert!(result.is_err())
+}
+
+#[test]
+fn missing_config_file_is_empty() {
+  let working_dir = tempdir().unwrap();
+  let result = load_config_file(&working_dir.path().join(CONFIG_FILE));
+
+  assert_eq!(result, Ok(ConfigFileToml::default()))
+}
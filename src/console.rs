@@ -46,6 +46,13 @@ impl<T: Clone> StatefulList<T> {
       self.state.select(None);
   }
 
+  pub fn is_last_selected(&self) -> bool {
+      self.state
+      .selected()
+      .map(|i| i + 1 == self.items.len())
+      .unwrap_or(false)
+  }
+
   pub fn get_selected(&self) -> Option<T> {
       self.state
       .selected()
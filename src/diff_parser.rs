@@ -0,0 +1,272 @@
+use unidiff::{PatchSet, PatchedFile};
+use crate::model::*;
+
+const DIFF_HEADER: &str = "diff --git ";
+const NULL_PATH: &str = "/dev/null";
+
+/// The information in the extended header lines of a single file in a git diff
+#[derive(Debug, Default)]
+struct FileHeader {
+  added: bool,
+  deleted: bool,
+  mode_changed: bool,
+  binary: bool,
+  renamed_from: Option<String>,
+  renamed_to: Option<String>,
+  copied_from: Option<String>,
+  copied_to: Option<String>
+}
+
+/// Parses a diff in the format of `git diff`, as returned by the GitHub API, into a diff per file
+pub fn parse_diffs(diff: &str) -> R<PullRequestDiff> {
+  file_sections(diff)
+    .into_iter()
+    .map(|section| parse_file_diff(&section))
+    .collect::<R<Vec<_>>>()
+    .map(PullRequestDiff)
+}
+
+/// Splits a diff into the lines of each file, starting with its `diff --git` line.
+/// Anything before the first file is ignored.
+fn file_sections(diff: &str) -> Vec<Vec<&str>> {
+  let mut sections: Vec<Vec<&str>> = vec![];
+
+  for line in diff.lines() {
+    if line.starts_with(DIFF_HEADER) {
+      sections.push(vec![line])
+    } else if let Some(section) = sections.last_mut() {
+      section.push(line)
+    }
+  }
+
+  sections
+}
+
+fn parse_file_diff(section: &[&str]) -> R<GitDiff> {
+  let diff_line = section[0];
+  let header = parse_file_header(&section[1..])?;
+
+  let unified_diff = section.iter().position(|line| line.starts_with("--- "));
+  let patched_file =
+    match unified_diff {
+      Some(start) => {
+        let mut patch = PatchSet::new();
+        patch.parse(section[start..].join("\n"))?;
+        patch.files().first().cloned()
+      },
+      None => None
+    };
+
+  let (old_path, new_path) =
+    match (&header.renamed_from, &header.renamed_to, &header.copied_from, &header.copied_to, &patched_file) {
+      (Some(from), Some(to), _, _, _) | (_, _, Some(from), Some(to), _) => (Some(from.clone()), Some(to.clone())),
+      (_, _, _, _, Some(file)) => (parse_path(&file.source_file, "a/")?, parse_path(&file.target_file, "b/")?),
+      _ => {
+        let (old_path, new_path) = parse_diff_line_paths(diff_line)?;
+        (Some(old_path).filter(|_| !header.added), Some(new_path).filter(|_| !header.deleted))
+      }
+    };
+
+  // Renames and copies take precedence, so the old path of a binary file that was renamed is not lost
+  let kind =
+    if header.renamed_from.is_some() {
+      DiffChangeKind::Renamed
+    } else if header.copied_from.is_some() {
+      DiffChangeKind::Copied
+    } else if header.binary {
+      DiffChangeKind::Binary
+    } else if header.added || old_path.is_none() {
+      DiffChangeKind::Added
+    } else if header.deleted || new_path.is_none() {
+      DiffChangeKind::Deleted
+    } else if header.mode_changed && patched_file.is_none() {
+      DiffChangeKind::ModeChange
+    } else {
+      DiffChangeKind::Modified
+    };
+
+  let file_name =
+    new_path
+      .clone()
+      .or_else(|| old_path.clone())
+      .ok_or_else(|| diff_parse_error(format!("Could not find the file name in: {}", diff_line)))?;
+
+  // Files without any changed lines, such as renames or binary files, only have their header lines
+  let (contents, added_lines, removed_lines, hunks) =
+    match patched_file {
+      Some(file) => (file.to_string(), file.added(), file.removed(), hunk_ranges(&file)),
+      None => (section.join("\n"), 0, 0, vec![])
+    };
+
+  Ok(
+    GitDiff {
+      file_name,
+      contents,
+      kind,
+      old_path,
+      new_path,
+      added_lines,
+      removed_lines,
+      hunks
+    }
+  )
+}
+
+/// The lengths are counted from the lines of each hunk, as unidiff reads a length left out of a hunk header as 0 instead of 1
+fn hunk_ranges(file: &PatchedFile) -> Vec<HunkRange> {
+  file
+    .hunks()
+    .iter()
+    .map(|h| {
+      HunkRange {
+        old_start: h.source_start,
+        old_lines: h.source_lines().len(),
+        new_start: h.target_start,
+        new_lines: h.target_lines().len()
+      }
+    })
+    .collect()
+}
+
+fn parse_file_header(lines: &[&str]) -> R<FileHeader> {
+  let mut header = FileHeader::default();
+
+  for line in lines {
+    if line.starts_with("--- ") || line.starts_with("@@") {
+      break;
+    } else if line.starts_with("new file mode ") {
+      header.added = true
+    } else if line.starts_with("deleted file mode ") {
+      header.deleted = true
+    } else if line.starts_with("old mode ") || line.starts_with("new mode ") {
+      header.mode_changed = true
+    } else if line.starts_with("Binary files ") || line.starts_with("GIT binary patch") {
+      header.binary = true
+    } else if let Some(path) = line.strip_prefix("rename from ") {
+      header.renamed_from = Some(unquote_path(path)?)
+    } else if let Some(path) = line.strip_prefix("rename to ") {
+      header.renamed_to = Some(unquote_path(path)?)
+    } else if let Some(path) = line.strip_prefix("copy from ") {
+      header.copied_from = Some(unquote_path(path)?)
+    } else if let Some(path) = line.strip_prefix("copy to ") {
+      header.copied_to = Some(unquote_path(path)?)
+    }
+  }
+
+  Ok(header)
+}
+
+/// Parses a path from a `---` or `+++` line, removing its `a/` or `b/` prefix. `/dev/null` has no path.
+fn parse_path(diff_path: &str, prefix: &str) -> R<Option<String>> {
+  if diff_path == NULL_PATH {
+    Ok(None)
+  } else {
+    let path = unquote_path(diff_path)?;
+    path
+      .strip_prefix(prefix)
+      .filter(|p| !p.is_empty())
+      .map(|p| Some(p.to_owned()))
+      .ok_or_else(|| diff_parse_error(format!("Expected a path starting with {} but got: {}", prefix, diff_path)))
+  }
+}
+
+/// Parses the old and new paths of a `diff --git a/<old> b/<new>` line.
+/// Unquoted paths can contain spaces, so they are split where both paths are the same,
+/// which is always the case for files that are not renamed or copied.
+fn parse_diff_line_paths(diff_line: &str) -> R<(String, String)> {
+  let paths = diff_line.trim_start_matches(DIFF_HEADER);
+  let invalid_line = || diff_parse_error(format!("Could not parse the paths in: {}", diff_line));
+
+  let (old_path, new_path) =
+    if paths.starts_with('"') {
+      let end = closing_quote(paths).ok_or_else(invalid_line)?;
+      let (old_path, new_path) = paths.split_at(end + 1);
+      (unquote_path(old_path)?, unquote_path(new_path.strip_prefix(' ').ok_or_else(invalid_line)?)?)
+    } else if let Some(start) = paths.find(" \"b/") {
+      (paths[..start].to_owned(), unquote_path(&paths[start + 1..])?)
+    } else {
+      paths
+        .match_indices(" b/")
+        .map(|(index, _)| paths.split_at(index))
+        .find(|(old_path, new_path)| old_path.get(2..) == new_path.get(3..))
+        .map(|(old_path, new_path)| (old_path.to_owned(), new_path[1..].to_owned()))
+        .ok_or_else(invalid_line)?
+    };
+
+  let strip = |path: &str, prefix: &str| path.strip_prefix(prefix).filter(|p| !p.is_empty()).map(|p| p.to_owned());
+  match (strip(&old_path, "a/"), strip(&new_path, "b/")) {
+    (Some(old_path), Some(new_path)) => Ok((old_path, new_path)),
+    _ => Err(invalid_line())
+  }
+}
+
+/// The index of the quote that closes a path starting with a quote
+fn closing_quote(path: &str) -> Option<usize> {
+  let mut escaped = false;
+  for (index, c) in path.char_indices().skip(1) {
+    match c {
+      '\\' if !escaped => escaped = true,
+      '"' if !escaped => return Some(index),
+      _ => escaped = false
+    }
+  }
+  None
+}
+
+/// Git quotes paths with unusual characters, such as `"tab\there"`, and escapes their bytes like C strings
+fn unquote_path(path: &str) -> R<String> {
+  let quoted =
+    match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+      Some(quoted) => quoted,
+      None => return Ok(path.to_owned())
+    };
+
+  let invalid_path = || diff_parse_error(format!("Invalid quoted path: {}", path));
+  let mut bytes: Vec<u8> = vec![];
+  let mut chars = quoted.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      let mut buffer = [0; 4];
+      bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+      continue;
+    }
+
+    let escaped = chars.next().ok_or_else(invalid_path)?;
+    let byte =
+      match escaped {
+        'a' => 0x07,
+        'b' => 0x08,
+        't' => b'\t',
+        'n' => b'\n',
+        'v' => 0x0b,
+        'f' => 0x0c,
+        'r' => b'\r',
+        '"' => b'"',
+        '\\' => b'\\',
+        '0'..='7' => {
+          let mut octal = escaped.to_string();
+          while octal.len() < 3 {
+            match chars.next_if(|c| ('0'..='7').contains(c)) {
+              Some(digit) => octal.push(digit),
+              None => break
+            }
+          }
+          u8::from_str_radix(&octal, 8).map_err(|_| invalid_path())?
+        },
+        _ => return Err(invalid_path())
+      };
+    bytes.push(byte);
+  }
+
+  String::from_utf8(bytes).map_err(|_| invalid_path())
+}
+
+fn diff_parse_error(message: String) -> PursError {
+  PursError::DiffParseError(NestedError::from(message))
+}
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
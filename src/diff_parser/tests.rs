@@ -0,0 +1,120 @@
+use crate::diff_parser::parse_diffs;
+use crate::model::*;
+use pretty_assertions::assert_eq;
+
+const DIFF: &str = r#"diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+index 587be6b..0000000
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-x
+diff --git a/image.png b/image.png
+index 87ae6b6..22f6b3b 100644
+Binary files a/image.png and b/image.png differ
+diff --git a/moved.txt b/renamed.txt
+similarity index 57%
+rename from moved.txt
+rename to renamed.txt
+index 4cb29ea..2f43848 100644
+--- a/moved.txt
++++ b/renamed.txt
+@@ -1,3 +1,3 @@
+ one
+ two
+-three
++THREE
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+diff --git "a/tab\tname.txt" "b/tab\tname.txt"
+new file mode 100644
+index 0000000..3e75765
+--- /dev/null
++++ "b/tab\tname.txt"
+@@ -0,0 +1 @@
++new
+diff --git a/with space.txt b/with space.txt
+index 1275430..a67a12a 100644
+--- a/with space.txt	
++++ b/with space.txt	
+@@ -1 +1,2 @@
+ same
++more
+diff --git a/original.txt b/copied.txt
+similarity index 100%
+copy from original.txt
+copy to copied.txt
+"#;
+
+fn summary(diff: &GitDiff) -> (String, DiffChangeKind, Option<String>, Option<String>, usize, usize) {
+  (diff.file_name.clone(), diff.kind, diff.old_path.clone(), diff.new_path.clone(), diff.added_lines, diff.removed_lines)
+}
+
+fn path(value: &str) -> Option<String> {
+  Some(value.to_owned())
+}
+
+#[test]
+fn parses_the_kind_paths_and_line_counts_of_each_file() {
+  let diffs = parse_diffs(DIFF).unwrap();
+  let summaries: Vec<_> = diffs.0.iter().map(summary).collect();
+
+  assert_eq!(
+    summaries,
+    vec![
+      ("gone.txt".to_owned(), DiffChangeKind::Deleted, path("gone.txt"), None, 0, 1),
+      ("image.png".to_owned(), DiffChangeKind::Binary, path("image.png"), path("image.png"), 0, 0),
+      ("renamed.txt".to_owned(), DiffChangeKind::Renamed, path("moved.txt"), path("renamed.txt"), 1, 1),
+      ("run.sh".to_owned(), DiffChangeKind::ModeChange, path("run.sh"), path("run.sh"), 0, 0),
+      ("tab\tname.txt".to_owned(), DiffChangeKind::Added, None, path("tab\tname.txt"), 1, 0),
+      ("with space.txt".to_owned(), DiffChangeKind::Modified, path("with space.txt"), path("with space.txt"), 1, 0),
+      ("copied.txt".to_owned(), DiffChangeKind::Copied, path("original.txt"), path("copied.txt"), 0, 0),
+    ]
+  );
+}
+
+#[test]
+fn keeps_the_unified_diff_of_changed_files() {
+  let diffs = parse_diffs(DIFF).unwrap();
+
+  assert_eq!(diffs.0[2].contents, "--- a/moved.txt\n+++ b/renamed.txt\n@@ -1,3 +1,3 @@ \n one\n two\n-three\n+THREE");
+  assert_eq!(diffs.0[3].contents, "diff --git a/run.sh b/run.sh\nold mode 100644\nnew mode 100755");
+}
+
+#[test]
+fn parses_the_hunk_ranges_of_each_file() {
+  let diffs = parse_diffs(DIFF).unwrap();
+
+  assert_eq!(diffs.0[0].hunks, vec![HunkRange { old_start: 1, old_lines: 1, new_start: 0, new_lines: 0 }]);
+  assert_eq!(diffs.0[5].hunks, vec![HunkRange { old_start: 1, old_lines: 1, new_start: 1, new_lines: 2 }]);
+  assert!(diffs.0[1].hunks.is_empty());
+}
+
+#[test]
+fn parses_an_empty_diff() {
+  assert!(parse_diffs("").unwrap().0.is_empty());
+}
+
+#[test]
+fn parses_quoted_paths_with_escaped_bytes() {
+  let diff = "diff --git \"a/caf\\303\\251.txt\" \"b/caf\\303\\251.txt\"\nold mode 100644\nnew mode 100755\n";
+
+  let diffs = parse_diffs(diff).unwrap();
+
+  assert_eq!(diffs.0[0].file_name, "café.txt");
+}
+
+#[test]
+fn fails_on_paths_without_a_prefix() {
+  let diff = "diff --git README.md README.md\nindex 1111111..2222222 100644\n--- README.md\n+++ README.md\n@@ -1 +1 @@\n-readme\n+updated readme\n";
+
+  assert!(matches!(parse_diffs(diff), Err(PursError::DiffParseError(_))));
+}
+
+#[test]
+fn fails_on_unparseable_diff_lines() {
+  let diff = "diff --git a/one.txt b/two.txt\nold mode 100644\nnew mode 100755\n";
+
+  assert!(matches!(parse_diffs(diff), Err(PursError::DiffParseError(_))));
+}
@@ -1,6 +1,7 @@
-use std::io::{self, ErrorKind};
-use std::path::Path;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
 use std::fs::{File, self};
+use serde::Deserialize;
 use crate::model::*;
 
 
@@ -17,8 +18,15 @@ pub fn create_file_and_path(file: &Path) -> R<File> {
   }
 }
 
+#[allow(clippy::useless_vec)]
 pub fn get_extract_path(config: &Config, pull: &ValidatedPullRequest) -> R<String> {
-    let repo_name = pull.repo_name.clone();
+    // PRs from a deleted fork are checked out from the base repository
+    let repo_name =
+      pull
+        .repo_name
+        .as_ref()
+        .map(|r| r.to_string())
+        .unwrap_or_else(|| pull.config_owner_repo.to_string());
     let branch_name = pull.branch_name.clone();
     let separator = format!("{}", std::path::MAIN_SEPARATOR);
     let extraction_path =
@@ -33,7 +41,208 @@ pub fn get_extract_path(config: &Config, pull: &ValidatedPullRequest) -> R<Strin
     Ok(extraction_path)
 }
 
+/// The directories to sparse checkout for a PR: the parent directory of each changed file and the always_include paths.
+/// Files in the root of the repository are always checked out.
+pub fn get_sparse_directories(diffs: &PullRequestDiff, always_include: &[String]) -> Vec<String> {
+    let changed_directories =
+      diffs
+        .0
+        .iter()
+        .filter_map(|d| Path::new(&d.file_name).parent())
+        .map(|parent| parent.to_string_lossy().to_string());
 
+    let always_include_directories = always_include.iter().map(|path| path.trim_matches('/').to_owned());
+
+    let mut directories: Vec<String> =
+      changed_directories
+        .chain(always_include_directories)
+        .filter(|directory| !directory.is_empty())
+        .collect();
+
+    directories.sort();
+    directories.dedup();
+    directories
+}
+
+/// The files generated next to the changed files. These are both exclude patterns and pathspecs.
+pub const GENERATED_FILE_PATTERNS: [&str; 4] = ["*.diff", "*.comment", DIFF_FILE_LIST, DIFF_MANIFEST];
+
+/// The patterns that ignore the files purs generates, either in the artifacts directory or next to the changed files
+pub fn get_generated_file_patterns(artifacts_dir: Option<&Path>) -> Vec<String> {
+    match artifacts_dir {
+      Some(artifacts_dir) => {
+        let components: Vec<String> = artifacts_dir.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+        vec![format!("/{}/", components.join("/"))]
+      },
+      None => GENERATED_FILE_PATTERNS.iter().map(|pattern| pattern.to_string()).collect()
+    }
+}
+
+/// The generated files listed in the diff manifest of a previous run
+#[derive(Deserialize)]
+struct PreviousDiffManifest {
+  files: Vec<PreviousDiffManifestFile>
+}
+
+#[derive(Deserialize)]
+struct PreviousDiffManifestFile {
+  diff_file: String,
+  comment_file: Option<String>
+}
+
+/// Removes the files a previous run wrote to the artifacts directory, as listed in its diff file list and diff manifest,
+/// and the directories they leave empty. Any other files in the artifacts directory are kept.
+pub fn remove_previous_artifacts(checkout_path: &Path, artifacts_dir: &Path) -> R<()> {
+    let file_list_path = artifacts_dir.join(DIFF_FILE_LIST);
+    let manifest_path = artifacts_dir.join(DIFF_MANIFEST);
+
+    let listed_files: Vec<String> =
+      read_previous_artifact(&checkout_path.join(&file_list_path))?
+        .map(|file_list| file_list.lines().map(|line| line.to_owned()).collect())
+        .unwrap_or_default();
+
+    let manifest_files: Vec<String> =
+      match read_previous_artifact(&checkout_path.join(&manifest_path))? {
+        Some(manifest) => {
+          serde_json::from_str::<PreviousDiffManifest>(&manifest)?
+            .files
+            .into_iter()
+            .flat_map(|file| std::iter::once(file.diff_file).chain(file.comment_file))
+            .collect()
+        },
+        None => vec![]
+      };
+
+    let previous_files =
+      listed_files
+        .iter()
+        .chain(manifest_files.iter())
+        .map(PathBuf::from)
+        .chain([file_list_path, manifest_path]);
+
+    for previous_file in previous_files {
+      // The lists can be edited, so only the files they list in the artifacts directory are removed
+      let relative_path =
+        match get_safe_generated_path(checkout_path, &previous_file) {
+          Ok(path) if path.starts_with(artifacts_dir) && path != artifacts_dir => path,
+          _ => continue
+        };
+
+      match fs::remove_file(checkout_path.join(&relative_path)) {
+        Ok(_) => (),
+        Err(e) if e.kind() == ErrorKind::NotFound => continue,
+        Err(e) => return Err(to_file_error(&format!("Could not remove {}", get_file_name(&relative_path)), e))
+      }
+
+      let empty_directories =
+        relative_path
+          .ancestors()
+          .skip(1)
+          .take_while(|directory| directory.starts_with(artifacts_dir) && *directory != artifacts_dir);
+
+      // Removing a directory fails once it is not empty
+      for directory in empty_directories {
+        if fs::remove_dir(checkout_path.join(directory)).is_err() {
+          break;
+        }
+      }
+    }
+
+    Ok(())
+}
+
+fn read_previous_artifact(file: &Path) -> R<Option<String>> {
+    match fs::read_to_string(file) {
+      Ok(contents) => Ok(Some(contents)),
+      Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+      Err(e) => Err(to_file_error(&format!("Could not read {}", get_file_name(file)), e))
+    }
+}
+
+/// Adds the patterns missing from a git exclude file, such as .git/info/exclude, creating the file if it doesn't exist
+pub fn add_exclude_patterns(exclude_file: &Path, patterns: &[String]) -> R<()> {
+    let existing_contents =
+      match fs::read_to_string(exclude_file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(to_file_error(&format!("Could not read {}", get_file_name(exclude_file)), e))
+      };
+
+    let missing_patterns: Vec<&String> =
+      patterns
+        .iter()
+        .filter(|pattern| !existing_contents.lines().any(|line| line.trim() == pattern.as_str()))
+        .collect();
+
+    if missing_patterns.is_empty() {
+      return Ok(())
+    }
+
+    let mut contents = existing_contents;
+    if !contents.is_empty() && !contents.ends_with('\n') {
+      contents.push('\n');
+    }
+    contents.push_str("# Generated by purs\n");
+    missing_patterns.iter().for_each(|pattern| {
+      contents.push_str(pattern);
+      contents.push('\n');
+    });
+
+    let mut file = create_file_and_path(exclude_file)?;
+    file
+      .write_all(contents.as_bytes())
+      .map_err(|e| to_file_error(&format!("Could not write {}", get_file_name(exclude_file)), e))
+}
+
+/// Normalises the path of a generated file, relative to the checkout. Paths from the API can't be trusted, so
+/// absolute paths, `..` and `.git` directories are refused, as are paths that go through a symlink to outside of the checkout.
+pub fn get_safe_generated_path(checkout_path: &Path, relative_path: &Path) -> R<PathBuf> {
+    let unsafe_path = |reason: &str| PursError::UnsafePath(format!("{}: {}", reason, relative_path.to_string_lossy()));
+
+    let mut safe_path = PathBuf::new();
+    for component in relative_path.components() {
+      match component {
+        Component::CurDir => (),
+        Component::Normal(name) if name.to_string_lossy().eq_ignore_ascii_case(".git") => return Err(unsafe_path("Refusing to write into a .git directory")),
+        Component::Normal(name) => safe_path.push(name),
+        Component::ParentDir => return Err(unsafe_path("Refusing to write to a path containing ..")),
+        Component::RootDir | Component::Prefix(_) => return Err(unsafe_path("Refusing to write to an absolute path"))
+      }
+    }
+
+    if safe_path.as_os_str().is_empty() {
+      return Err(unsafe_path("Refusing to write to an empty path"))
+    }
+
+    // Tracked symlinks in the checkout could point anywhere
+    let full_path = checkout_path.join(&safe_path);
+    if fs::symlink_metadata(&full_path).map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+      return Err(unsafe_path("Refusing to write through a symlink"))
+    }
+
+    let canonical_checkout_path =
+      checkout_path
+        .canonicalize()
+        .map_err(|e| to_file_error(&format!("Could not resolve {}", get_file_name(checkout_path)), e))?;
+
+    let existing_ancestor = full_path.ancestors().find(|ancestor| ancestor.exists()).unwrap_or(checkout_path);
+    // A symlinked directory could also point into the .git directory of the checkout
+    let is_inside_checkout =
+      existing_ancestor
+        .canonicalize()
+        .ok()
+        .and_then(|ancestor| ancestor.strip_prefix(&canonical_checkout_path).map(|p| p.to_path_buf()).ok())
+        .map(|ancestor| !ancestor.components().any(|c| c.as_os_str().to_string_lossy().eq_ignore_ascii_case(".git")))
+        .unwrap_or(false);
+
+    if is_inside_checkout {
+      Ok(safe_path)
+    } else {
+      Err(unsafe_path("Refusing to write outside the checkout"))
+    }
+}
+
+#[allow(clippy::needless_return)]
 fn try_create_parent_directories(file: &Path, e: io::Error) -> R<File> {
   match e.kind() {
     ErrorKind::NotFound => {
@@ -68,3 +277,8 @@ fn try_create_parent_directories(file: &Path, e: io::Error) -> R<File> {
 fn get_file_name(file_path: &Path) -> String {
   file_path.to_string_lossy().to_string()
 }
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
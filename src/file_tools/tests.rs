@@ -0,0 +1,162 @@
+use crate::file_tools::{get_sparse_directories, get_generated_file_patterns, add_exclude_patterns, get_safe_generated_path, remove_previous_artifacts};
+use crate::model::*;
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn diff(file_name: &str) -> GitDiff {
+  GitDiff {
+    file_name: file_name.to_owned(),
+    contents: String::new(),
+    kind: DiffChangeKind::Modified,
+    old_path: Some(file_name.to_owned()),
+    new_path: Some(file_name.to_owned()),
+    added_lines: 0,
+    removed_lines: 0,
+    hunks: vec![]
+  }
+}
+
+#[test]
+fn gets_sparse_directories_of_changed_files() {
+  let diffs =
+    PullRequestDiff(
+      vec![
+        diff("README.md"),
+        diff("modules/core/src/Main.scala"),
+        diff("modules/core/src/Other.scala"),
+        diff("docs/index.md"),
+      ]
+    );
+
+  let always_include = vec!["project/".to_owned(), "docs".to_owned()];
+
+  assert_eq!(
+    get_sparse_directories(&diffs, &always_include),
+    vec!["docs".to_owned(), "modules/core/src".to_owned(), "project".to_owned()]
+  );
+}
+
+#[test]
+fn gets_no_sparse_directories_for_root_files() {
+  let diffs = PullRequestDiff(vec![diff("README.md")]);
+
+  assert!(get_sparse_directories(&diffs, &[]).is_empty());
+}
+
+#[test]
+fn gets_generated_file_patterns() {
+  assert_eq!(get_generated_file_patterns(Some(Path::new(".purs/review"))), vec!["/.purs/review/".to_owned()]);
+  assert_eq!(
+    get_generated_file_patterns(None),
+    vec!["*.diff".to_owned(), "*.comment".to_owned(), "diff_file_list.txt".to_owned(), "diff_manifest.json".to_owned()]
+  );
+}
+
+#[test]
+fn adds_missing_exclude_patterns_once() {
+  let dir = tempdir().unwrap();
+  let exclude_file = dir.path().join("info").join("exclude");
+  let patterns = vec!["/.purs/".to_owned(), "*.diff".to_owned()];
+
+  add_exclude_patterns(&exclude_file, &patterns[..1]).unwrap();
+  add_exclude_patterns(&exclude_file, &patterns).unwrap();
+  add_exclude_patterns(&exclude_file, &patterns).unwrap();
+
+  assert_eq!(
+    fs::read_to_string(&exclude_file).unwrap(),
+    "# Generated by purs\n/.purs/\n# Generated by purs\n*.diff\n"
+  );
+}
+
+#[test]
+fn keeps_existing_exclude_patterns() {
+  let dir = tempdir().unwrap();
+  let exclude_file = dir.path().join("exclude");
+  fs::write(&exclude_file, "# git ls-files --others --exclude-from=.git/info/exclude\n*.log").unwrap();
+
+  add_exclude_patterns(&exclude_file, &["/.purs/".to_owned()]).unwrap();
+
+  assert_eq!(
+    fs::read_to_string(&exclude_file).unwrap(),
+    "# git ls-files --others --exclude-from=.git/info/exclude\n*.log\n# Generated by purs\n/.purs/\n"
+  );
+}
+
+#[test]
+fn normalises_safe_generated_paths() {
+  let checkout = tempdir().unwrap();
+
+  assert_eq!(get_safe_generated_path(checkout.path(), Path::new("src/main.rs.diff")).unwrap(), PathBuf::from("src/main.rs.diff"));
+  assert_eq!(get_safe_generated_path(checkout.path(), Path::new("./.purs/./README.md.diff")).unwrap(), PathBuf::from(".purs/README.md.diff"));
+  assert_eq!(get_safe_generated_path(checkout.path(), Path::new("docs/.github/workflow.yml.diff")).unwrap(), PathBuf::from("docs/.github/workflow.yml.diff"));
+}
+
+#[test]
+fn refuses_generated_paths_outside_checkout_or_in_git_directory() {
+  let checkout = tempdir().unwrap();
+  let is_unsafe = |path: &str| matches!(get_safe_generated_path(checkout.path(), Path::new(path)), Err(PursError::UnsafePath(_)));
+
+  assert!(is_unsafe("../outside.diff"));
+  assert!(is_unsafe("src/../../outside.diff"));
+  assert!(is_unsafe("/etc/passwd.diff"));
+  assert!(is_unsafe(".git/hooks/pre-commit.diff"));
+  assert!(is_unsafe("src/.GIT/config.diff"));
+  assert!(is_unsafe("."));
+}
+
+#[cfg(unix)]
+#[test]
+fn refuses_generated_paths_through_symlinks() {
+  let outside = tempdir().unwrap();
+  let checkout = tempdir().unwrap();
+  fs::create_dir(checkout.path().join(".git")).unwrap();
+  std::os::unix::fs::symlink(outside.path(), checkout.path().join("linked")).unwrap();
+  std::os::unix::fs::symlink(checkout.path().join(".git"), checkout.path().join("hooks")).unwrap();
+  std::os::unix::fs::symlink(outside.path().join("file"), checkout.path().join("README.md.diff")).unwrap();
+  let is_unsafe = |path: &str| matches!(get_safe_generated_path(checkout.path(), Path::new(path)), Err(PursError::UnsafePath(_)));
+
+  assert!(is_unsafe("linked/main.rs.diff"));
+  assert!(is_unsafe("hooks/pre-commit.diff"));
+  assert!(is_unsafe("README.md.diff"));
+}
+
+#[test]
+fn removes_only_the_listed_files_of_the_previous_run() {
+  let checkout = tempdir().unwrap();
+  let artifacts = checkout.path().join(".purs");
+  fs::create_dir_all(artifacts.join("src").join("model")).unwrap();
+  fs::create_dir_all(artifacts.join("notes")).unwrap();
+
+  fs::write(artifacts.join("src").join("main.rs.diff"), "diff").unwrap();
+  fs::write(artifacts.join("src").join("main.rs.comment"), "comment").unwrap();
+  fs::write(artifacts.join("src").join("model").join("pr.rs.diff"), "diff").unwrap();
+  fs::write(artifacts.join("notes").join("review.md"), "my notes").unwrap();
+  fs::write(checkout.path().join("README.md"), "readme").unwrap();
+
+  fs::write(artifacts.join(DIFF_FILE_LIST), ".purs/src/main.rs.diff\n.purs/src/model/pr.rs.diff\nREADME.md\n../outside.txt\n").unwrap();
+  let manifest = r#"{ "files": [{ "file_name": "src/main.rs", "diff_file": ".purs/src/main.rs.diff", "comment_file": ".purs/src/main.rs.comment" }] }"#;
+  fs::write(artifacts.join(DIFF_MANIFEST), manifest).unwrap();
+
+  remove_previous_artifacts(checkout.path(), Path::new(".purs")).unwrap();
+
+  assert!(!artifacts.join("src").exists());
+  assert!(!artifacts.join(DIFF_FILE_LIST).exists());
+  assert!(!artifacts.join(DIFF_MANIFEST).exists());
+  assert!(artifacts.join("notes").join("review.md").exists());
+  assert!(checkout.path().join("README.md").exists());
+}
+
+#[test]
+fn removes_nothing_without_a_previous_run() {
+  let checkout = tempdir().unwrap();
+  let artifacts = checkout.path().join(".purs");
+  fs::create_dir_all(&artifacts).unwrap();
+  fs::write(artifacts.join("notes.md"), "my notes").unwrap();
+
+  remove_previous_artifacts(checkout.path(), Path::new(".purs")).unwrap();
+  remove_previous_artifacts(checkout.path(), Path::new("missing")).unwrap();
+
+  assert!(artifacts.join("notes.md").exists());
+}
@@ -1,28 +1,38 @@
 use crate::model::*;
-use crate::file_tools::{create_file_and_path, to_file_error};
+use crate::file_tools::{create_file_and_path, to_file_error, get_safe_generated_path};
 
 use std::collections::HashMap;
 use std::io::Write;
 use std::path::Path;
-use std::fs::File;
 use std::time::Instant;
 
 
+/// The diff files, the diff file list and the diff manifest are written to the subdirectory of the checkout. The file names in the list are relative to the checkout.
+/// The comments are only used to describe the comment files in the manifest, and are None if comments are not included.
 // TODO: Do we want the diff file to be configurable?
-pub fn write_diff_files(checkout_path: &str, diffs: &PullRequestDiff) -> R<()> {
+pub fn write_diff_files(checkout_path: &str, subdirectory: &Path, diffs: &PullRequestDiff, comments: Option<&Comments>) -> R<()> {
   println!("Generating diff files...");
 
   let write_start = Instant::now();
 
-  let file_list_path = Path::new(checkout_path).join(DIFF_FILE_LIST);
+  // Check every path before writing any files, so an unsafe path doesn't leave half of the files behind
+  let file_list_path = Path::new(checkout_path).join(get_safe_generated_path(Path::new(checkout_path), &subdirectory.join(DIFF_FILE_LIST))?);
+  let generated_files =
+    diffs
+      .0
+      .iter()
+      .map(|d| get_generated_file_paths(checkout_path, subdirectory, d, comments))
+      .collect::<R<Vec<_>>>()?;
+
   // TODO: Do we want to wrap this error?
-  let mut file_list = File::create(&file_list_path) .unwrap();
+  let mut file_list = create_file_and_path(&file_list_path).unwrap();
 
-  diffs.0.iter().for_each(|d| {
-      writeln!(file_list, "{}.diff", d.file_name).unwrap(); // TODO: Do we want to wrap this error?
+  generated_files.iter().for_each(|generated| {
+      let d = generated.diff;
+      let diff_file_name = &generated.diff_file;
+      writeln!(file_list, "{}", diff_file_name).unwrap(); // TODO: Do we want to wrap this error?
 
-      let diff_file_name = format!("{}.diff", d.file_name);
-      let diff_file = Path::new(checkout_path).join(&diff_file_name);
+      let diff_file = Path::new(checkout_path).join(diff_file_name);
 
       let mut f = create_file_and_path(&diff_file).unwrap();
 
@@ -40,6 +50,8 @@ pub fn write_diff_files(checkout_path: &str, diffs: &PullRequestDiff) -> R<()> {
         .unwrap();
   });
 
+  write_diff_manifest(checkout_path, subdirectory, &generated_files, comments)?;
+
   let time_taken = write_start.elapsed().as_millis();
   println!("Writing diff files took {} ms", time_taken);
 
@@ -47,7 +59,38 @@ pub fn write_diff_files(checkout_path: &str, diffs: &PullRequestDiff) -> R<()> {
 }
 
 
-pub fn write_comment_files(checkout_path: &str, comments: &Comments, avatar_hash: HashMap<Url, FileUrl>) -> R<()> {
+/// The paths of the diff file of a changed file and of its comment file, if it has one. write_comment_files uses the same paths.
+fn get_generated_file_paths<'a>(checkout_path: &str, subdirectory: &Path, d: &'a GitDiff, comments: Option<&Comments>) -> R<GeneratedFilePaths<'a>> {
+  let generated_path = |extension: &str| {
+    get_safe_generated_path(Path::new(checkout_path), &subdirectory.join(format!("{}.{}", d.file_name, extension)))
+      .map(|path| path.to_string_lossy().to_string())
+  };
+
+  let has_comment_file = comments.map(|c| c.line_comment_count(&d.file_name) > 0).unwrap_or(false);
+
+  Ok(
+    GeneratedFilePaths {
+      diff: d,
+      diff_file: generated_path("diff")?,
+      comment_file: if has_comment_file { Some(generated_path("comment")?) } else { None }
+    }
+  )
+}
+
+
+fn write_diff_manifest(checkout_path: &str, subdirectory: &Path, generated_files: &[GeneratedFilePaths], comments: Option<&Comments>) -> R<()> {
+  let manifest_file_name = get_safe_generated_path(Path::new(checkout_path), &subdirectory.join(DIFF_MANIFEST))?.to_string_lossy().to_string();
+  let manifest = DiffManifestJson::new(generated_files, comments);
+  let contents = serde_json::to_string_pretty(&manifest)?;
+
+  let mut mf = create_file_and_path(&Path::new(checkout_path).join(&manifest_file_name))?;
+  println!("Creating {}", &manifest_file_name);
+  mf.write_all(contents.as_bytes())
+    .map_err(|e| to_file_error(&format!("Could not write {}", manifest_file_name), e))
+}
+
+
+pub fn write_comment_files(checkout_path: &str, subdirectory: &Path, comments: &Comments, avatar_hash: HashMap<Url, FileUrl>) -> R<()> {
   if !comments.is_empty() {
     println!("Generating comment files...");
 
@@ -55,13 +98,21 @@ pub fn write_comment_files(checkout_path: &str, comments: &Comments, avatar_hash
 
     let file_comments_json = CommentJson::grouped_by_line_2(comments.clone(), avatar_hash);
 
-    file_comments_json.into_iter().for_each(|file_comments_json|{
-      let comment_file_name = format!("{}.comment", file_comments_json.file_name);
+    let comment_file_names =
+      file_comments_json
+        .into_iter()
+        .map(|file_comments_json| {
+          get_safe_generated_path(Path::new(checkout_path), &subdirectory.join(format!("{}.comment", file_comments_json.file_name)))
+            .map(|comment_file_name| (file_comments_json, comment_file_name.to_string_lossy().to_string()))
+        })
+        .collect::<R<Vec<_>>>()?;
+
+    comment_file_names.into_iter().for_each(|(file_comments_json, comment_file_name)|{
       let comment_file = Path::new(checkout_path).join(&comment_file_name);
 
       match serde_json::to_string_pretty(&file_comments_json) {
         Ok(contents) => {
-          let mut cf = File::create(&comment_file).unwrap(); // TODO: Do we want to wrap this error?
+          let mut cf = create_file_and_path(&comment_file).unwrap(); // TODO: Do we want to wrap this error?
           println!("Creating {}", &comment_file_name);
           let buf: &[u8]= contents.as_ref();
           cf.write_all(buf).unwrap(); // TODO: Do we want to wrap this error?
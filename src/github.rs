@@ -3,25 +3,100 @@ use futures::future::{try_join_all, join_all};
 use octocrab::{self, Octocrab};
 use octocrab::params;
 use octocrab::models::pulls::ReviewState as GHReviewState;
+use octocrab::models::IssueState;
+use chrono::Utc;
 use crate::model::*;
-use unidiff::PatchSet;
+use crate::diff_parser::parse_diffs;
 use futures::stream::{self, StreamExt};
-use crate::tools::partition;
+use octocrab::models::issues::Issue;
+use crate::tools::{partition, glob_match, dedup};
 use std::time::Instant;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
-type PageHandles = Vec<tokio::task::JoinHandle<Result<(octocrab::Page<octocrab::models::pulls::PullRequest>, OwnerRepo), PursError>>>;
+// The maximum page size supported by the GitHub API
+const MAX_PER_PAGE: usize = 100;
 
-pub async fn get_prs3(config: &Config, octocrab: Octocrab) -> R<Vec<PullRequest>> {
+// The maximum number of pages followed per repository each time PRs are fetched, so filters that match few PRs don't page
+// through every PR of a repository. The remaining pages can be loaded on demand.
+const MAX_PAGES_PER_FETCH: usize = 10;
+
+// Open PRs in repositories that are still maintained
+const INBOX_QUERY: &str = "is:pr is:open archived:false";
+
+type GHPullRequest = octocrab::models::pulls::PullRequest;
+
+type PageHandles = Vec<tokio::task::JoinHandle<Result<(PullsPage, OwnerRepo), PursError>>>;
+
+/// Where listing the PRs of each repository stopped, so that more PRs can be fetched on demand
+#[derive(Debug, Clone, Default)]
+pub struct MorePullRequests {
+    cursors: Vec<PullsCursor>,
+    filters: PrFilters,
+    limit: PrLimit
+}
+
+impl MorePullRequests {
+    pub fn has_more(&self) -> bool {
+      self.cursors.iter().any(PullsCursor::has_more)
+    }
+}
+
+/// The PRs of a page that have not been consumed yet, along with the link to the next page
+#[derive(Debug, Clone)]
+struct PullsCursor {
+    owner_repo: OwnerRepo,
+    page: PullsPage
+}
+
+impl PullsCursor {
+    fn has_more(&self) -> bool {
+      match &self.page {
+        PullsPage::List(page) => page.has_more(),
+        PullsPage::Search(page) => page.has_more()
+      }
+    }
+}
+
+/// PRs are listed through the pulls API, unless the filters need the search API
+#[derive(Debug, Clone)]
+enum PullsPage {
+    List(PageCursor<GHPullRequest>),
+    // Search results are already filtered, but don't have the details of each PR
+    Search(PageCursor<Issue>)
+}
+
+/// The items of a page that have not been consumed yet, along with the page they were taken from, which links to the next page
+#[derive(Debug, Clone)]
+struct PageCursor<T> {
+    items: VecDeque<T>,
+    page: octocrab::Page<T>
+}
+
+impl <T> PageCursor<T> {
+    fn empty() -> Self {
+      PageCursor { items: VecDeque::new(), page: octocrab::Page::default() }
+    }
+
+    fn has_more(&self) -> bool {
+      !self.items.is_empty() || self.page.next.is_some()
+    }
+}
+
+impl <T> From<octocrab::Page<T>> for PageCursor<T> {
+    fn from(mut page: octocrab::Page<T>) -> Self {
+      PageCursor { items: page.take_items().into(), page }
+    }
+}
+
+pub async fn get_prs3(repositories: &NonEmptyVec<OwnerRepo>, filters: &PrFilters, limit: PrLimit, octocrab: Octocrab) -> R<(Vec<PullRequest>, MorePullRequests)> {
     let page_handles:PageHandles  =
-      config
-      .repositories
+      repositories
       .to_vec()
       .into_iter()
       .map(|owner_repo| {
         tokio::task::spawn(
       get_pulls(
-              octocrab.clone(), owner_repo.clone()
+              octocrab.clone(), owner_repo.clone(), filters.clone(), limit
             )
             .map(|hr| { hr.map(|h| (h, owner_repo)) }) //write a help function for this
         )
@@ -32,84 +107,197 @@ pub async fn get_prs3(config: &Config, octocrab: Octocrab) -> R<Vec<PullRequest>
       .await
       .map_err( PursError::from)?;
 
-    let page_repos =
-      page_results
+    let (page_repos, page_errors) = partition(page_results);
+
+    if !page_errors.is_empty() {
+      return Err(PursError::MultipleErrors(page_errors))
+    }
+
+    let cursors =
+      page_repos
       .into_iter()
-      //TODO: Do we need to handle the errors of this?
-      .map(|rp| rp.unwrap())
+      .map(|(page, owner_repo)| PullsCursor { owner_repo, page })
       .collect::<Vec<_>>();
 
-    let async_parts = page_repos.iter().map(|(page, OwnerRepo(owner, repo))| {
-            page.into_iter().map(|pull| {
-                let pr_no = pull.number;
-                let reviews_handle = tokio::spawn(get_reviews2(octocrab.clone(), owner.clone(), repo.clone(), pr_no));
-                let comments_handle = tokio::spawn(get_comments2(octocrab.clone(), owner.clone(), repo.clone(), pr_no));
-                let diffs_handle = tokio::spawn(get_pr_diffs2(octocrab.clone(), owner.clone(), repo.clone(), pr_no));
-
-                AsyncPullRequestParts {
-                    owner_repo: OwnerRepo(owner.clone(), repo.clone()),
-                    pull: pull.clone(),
-                    reviews_handle,
-                    comments_handle,
-                    diffs_handle
-                }
-            }).collect::<Vec<_>>()
-    });
-
-    let parts = async_parts.flatten().collect::<Vec<_>>();
-    let parts_stream = stream::iter(parts);
+    let more =
+      MorePullRequests {
+        cursors,
+        filters: filters.clone(),
+        limit
+      };
 
-    let pr_stream =
-        parts_stream.then(|AsyncPullRequestParts { owner_repo, pull, reviews_handle, comments_handle, diffs_handle }|{
-            async move {
-                let res = tokio::try_join!(
-                    flatten(reviews_handle),
-                    flatten(comments_handle),
-                    flatten(diffs_handle)
-                );
-
-                match res {
-                  Ok((reviews, comments, diffs)) => {
-
-                    let pr_no = pull.number;
-                    let title = pull.title.clone().unwrap_or_else(|| "-".to_string());
-                    let ssh_url = pull.head.repo.clone().and_then(|r| (r.ssh_url));
-                    let head_sha = pull.head.sha;
-                    let repo_name = pull.head.repo.clone().and_then(|r| r.full_name);
-                    let branch_name = pull.head.ref_field;
-                    let base_sha = pull.base.sha;
-                    let config_owner_repo = owner_repo;
-                    let draft = pull.draft;
-                    let created_at = pull.created_at;
-                    let updated_at = pull.updated_at;
-                    let pr_owner = create_user(pull.user.clone().as_deref());
-
-                    let pr =
-                      PullRequest {
-                        config_owner_repo,
-                        pr_owner,
-                        title,
-                        pr_number: pr_no,
-                        ssh_url,
-                        branch_name,
-                        head_sha,
-                        repo_name,
-                        base_sha,
-                        reviews,
-                        comments,
-                        diffs,
-                        draft,
-                        created_at,
-                        updated_at
-                      };
-
-                    Ok(pr)
-                  },
-                  Err(error) => Err(error),
-              }
-            }
+    get_more_prs(more, octocrab).await
+}
+
+/// Fetches up to the limit of matching PRs per repository, continuing from where the previous fetch stopped
+pub async fn get_more_prs(more: MorePullRequests, octocrab: Octocrab) -> R<(Vec<PullRequest>, MorePullRequests)> {
+    let MorePullRequests { cursors, filters, limit } = more;
+
+    let pull_handles =
+      cursors
+        .into_iter()
+        .map(|cursor| {
+          tokio::task::spawn(
+            take_pulls(octocrab.clone(), cursor, filters.clone(), limit)
+          )
         });
 
+    let pull_results =
+      try_join_all(pull_handles)
+      .await
+      .map_err( PursError::from)?;
+
+    let (taken_pulls, pull_errors) = partition(pull_results);
+
+    if !pull_errors.is_empty() {
+      return Err(PursError::MultipleErrors(pull_errors))
+    }
+
+    // Only fetch the details of PRs that match the filters
+    let parts =
+      taken_pulls
+        .iter()
+        .flat_map(|(pulls, cursor)| {
+          pulls
+            .iter()
+            .map(|pull| get_pull_request_parts(&octocrab, &cursor.owner_repo, pull))
+        })
+        .collect();
+
+    let pull_requests = to_pull_requests(parts).await?;
+
+    let cursors =
+      taken_pulls
+        .into_iter()
+        .map(|(_, cursor)| cursor)
+        .filter(PullsCursor::has_more)
+        .collect();
+
+    let more = MorePullRequests { cursors, filters, limit };
+
+    Ok((pull_requests, more))
+}
+
+/// Searches all accessible repositories for open PRs where a review was requested from the current user, or where the current user
+/// was mentioned or reviewed the PR and it changed since they last reviewed it
+pub async fn get_inbox_prs(filters: &PrFilters, limit: PrLimit, octocrab: Octocrab) -> R<Vec<PullRequest>> {
+    let current_user = octocrab.current().user().await.map_err(PursError::from)?.login;
+
+    let qualifiers = filter_qualifiers(filters).join(" ");
+    let review_requested_query = format!("{} review-requested:@me {}", INBOX_QUERY, qualifiers);
+    let mentioned_query = format!("{} mentions:@me -author:@me {}", INBOX_QUERY, qualifiers);
+    let reviewed_query = format!("{} reviewed-by:@me -author:@me {}", INBOX_QUERY, qualifiers);
+
+    let (review_requested, mentioned, reviewed) =
+      tokio::try_join!(
+        search_pull_requests(&octocrab, &review_requested_query, limit),
+        search_pull_requests(&octocrab, &mentioned_query, limit),
+        search_pull_requests(&octocrab, &reviewed_query, limit)
+      )?;
+
+    let review_requested = dedup(review_requested);
+    let mentioned_or_reviewed =
+      dedup(mentioned.iter().cloned().chain(reviewed).collect())
+        .into_iter()
+        .filter(|pr| !review_requested.contains(pr))
+        .collect::<Vec<_>>();
+
+    let review_requested_pulls = try_join_all(review_requested.iter().map(|pr| get_pull(&octocrab, pr))).await?;
+    let mentioned_or_reviewed_pulls = try_join_all(mentioned_or_reviewed.iter().map(|pr| get_pull(&octocrab, pr))).await?;
+
+    // Only keep mentioned and reviewed PRs that have new commits since they were last reviewed.
+    // Mentioned PRs that were not reviewed yet are kept.
+    let mentioned_or_reviewed_reviews =
+      try_join_all(
+        mentioned_or_reviewed
+          .iter()
+          .map(|pr| get_reviews2(octocrab.clone(), pr.owner_repo.0.clone(), pr.owner_repo.1.clone(), pr.pr_number))
+      ).await?;
+
+    let changed_since_reviewed =
+      mentioned_or_reviewed
+        .iter()
+        .zip(mentioned_or_reviewed_pulls.iter())
+        .zip(mentioned_or_reviewed_reviews.iter())
+        .filter(|((pr, pull), reviews)| {
+          let unreviewed_mention = mentioned.contains(pr) && !reviews.reviewed_by(&current_user);
+          unreviewed_mention || reviews.changed_since_reviewed_by(&current_user, &pull.head.sha)
+        })
+        .map(|(pr_pull, _)| pr_pull);
+
+    let parts =
+      review_requested
+        .iter()
+        .zip(review_requested_pulls.iter())
+        .chain(changed_since_reviewed)
+        .filter(|(_, pull)| matches_filters(pull, filters))
+        .map(|(pr, pull)| get_pull_request_parts(&octocrab, &pr.owner_repo, pull))
+        .collect();
+
+    to_pull_requests(parts).await
+}
+
+/// Searches for PRs, returning at most the limit of results
+async fn search_pull_requests(octocrab: &Octocrab, query: &str, limit: PrLimit) -> R<Vec<DirectPr>> {
+    let mut page = search_issues(octocrab, query, "updated", limit).await?;
+
+    let mut issues = page.take_items();
+
+    while issues.len() < limit.value() {
+      match octocrab.get_page::<Issue>(&page.next).await.map_err(PursError::from)? {
+        Some(mut next_page) => {
+          issues.append(&mut next_page.take_items());
+          page = next_page
+        },
+        None => break
+      }
+    }
+
+    let prs =
+      issues
+        .iter()
+        .take(limit.value())
+        .filter_map(to_direct_pr)
+        .collect();
+
+    Ok(prs)
+}
+
+async fn search_issues(octocrab: &Octocrab, query: &str, sort: &str, limit: PrLimit) -> R<octocrab::Page<Issue>> {
+    octocrab
+      .search()
+      .issues_and_pull_requests(query)
+      .sort(sort)
+      .order("desc")
+      .per_page(limit.value().min(MAX_PER_PAGE) as u8)
+      .send()
+      .await
+      .map_err(PursError::from)
+}
+
+fn to_direct_pr(issue: &Issue) -> Option<DirectPr> {
+    // Issues that are not PRs don't have a PR link
+    issue.pull_request.as_ref()?;
+
+    // The repository url ends with: /repos/<owner>/<repo>
+    let mut segments = issue.repository_url.path_segments()?.rev();
+    let repo = segments.next()?;
+    let owner = segments.next()?;
+
+    Some(
+      DirectPr {
+        owner_repo: OwnerRepo(Owner(owner.to_owned()), Repo(repo.to_owned())),
+        pr_number: issue.number
+      }
+    )
+}
+
+/// Waits for the reviews, comments and diffs of each PR
+async fn to_pull_requests(parts: Vec<AsyncPullRequestParts>) -> R<Vec<PullRequest>> {
+    let parts_stream = stream::iter(parts);
+
+    let pr_stream = parts_stream.then(to_pull_request);
+
 
     let results_with_errors: Vec<R<PullRequest>> = pr_stream.collect().await;
 
@@ -123,6 +311,311 @@ pub async fn get_prs3(config: &Config, octocrab: Octocrab) -> R<Vec<PullRequest>
     }
 }
 
+/// Takes matching PRs from the cursor, following the next page links until the limit is reached or there are no more PRs
+async fn take_pulls(octocrab: Octocrab, cursor: PullsCursor, filters: PrFilters, limit: PrLimit) -> R<(Vec<GHPullRequest>, PullsCursor)> {
+    let PullsCursor { owner_repo, page } = cursor;
+
+    match page {
+      PullsPage::List(page) => {
+        let (pulls, page) =
+          take_items(&octocrab, page, limit, |pull| {
+            // PRs are sorted by when they were updated, so all remaining PRs are too old
+            if is_updated_before_since(pull, &filters) {
+              TakeItem::Stop
+            } else if matches_filters(pull, &filters) {
+              TakeItem::Take
+            } else {
+              TakeItem::Skip
+            }
+          }).await?;
+
+        Ok((pulls, PullsCursor { owner_repo, page: PullsPage::List(page) }))
+      },
+      PullsPage::Search(page) => {
+        let (issues, page) = take_items(&octocrab, page, limit, |_| TakeItem::Take).await?;
+        let direct_prs = issues.iter().filter_map(to_direct_pr).collect::<Vec<_>>();
+        let pulls = try_join_all(direct_prs.iter().map(|pr| get_pull(&octocrab, pr))).await?;
+
+        Ok((pulls, PullsCursor { owner_repo, page: PullsPage::Search(page) }))
+      }
+    }
+}
+
+enum TakeItem {
+    Take,
+    Skip,
+    // Stops at this item and discards the remaining items
+    Stop
+}
+
+/// Takes items from the page, following the next page links until the limit is reached, there are no more items or
+/// MAX_PAGES_PER_FETCH pages have been fetched
+async fn take_items<T: serde::de::DeserializeOwned>(octocrab: &Octocrab, mut page: PageCursor<T>, limit: PrLimit, take_item: impl Fn(&T) -> TakeItem) -> R<(Vec<T>, PageCursor<T>)> {
+    let mut items = vec![];
+    let mut fetched_pages = 0;
+
+    while items.len() < limit.value() {
+      match page.items.pop_front() {
+        Some(item) => {
+          match take_item(&item) {
+            TakeItem::Take => items.push(item),
+            TakeItem::Skip => {},
+            TakeItem::Stop => {
+              page = PageCursor::empty();
+              break
+            }
+          }
+        },
+        None if fetched_pages == MAX_PAGES_PER_FETCH => break,
+        None => {
+          match octocrab.get_page(&page.page.next).await.map_err(PursError::from)? {
+            Some(next_page) => {
+              page = PageCursor::from(next_page);
+              fetched_pages += 1
+            },
+            None => break
+          }
+        }
+      }
+    }
+
+    Ok((items, page))
+}
+
+fn is_updated_before_since(pull: &GHPullRequest, filters: &PrFilters) -> bool {
+    filters
+      .since
+      .zip(pull.updated_at)
+      .map(|(since, updated_at)| Utc::now().signed_duration_since(updated_at) > since)
+      .unwrap_or(false)
+}
+
+/// Open PRs are listed newest first, unless --since is supplied. Then PRs are sorted by when they were updated,
+/// so that listing can stop at the first PR that was updated before --since.
+fn sorts_by_updated(filters: &PrFilters) -> bool {
+    filters.since.is_some() || filters.state != PrStateFilter::Open
+}
+
+/// Checks that the token works, which scopes it has, the remaining rate limit and whether each repository can be accessed
+pub async fn get_access_report(octocrab: &Octocrab, repositories: &[OwnerRepo]) -> R<AccessReport> {
+    let response = octocrab._get("/user").await.map_err(PursError::from)?;
+    let status = response.status();
+
+    if status.as_u16() == 401 {
+      return Err(PursError::AccessError("The GitHub token was rejected. Check that it has not expired or been revoked".to_owned()))
+    }
+
+    // Only classic tokens report their scopes
+    let scopes =
+      response
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|scopes| scopes.to_str().ok())
+        .map(|scopes| {
+          scopes
+            .split(',')
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+        });
+
+    let body = octocrab.body_to_string(response).await.map_err(PursError::from)?;
+
+    if !status.is_success() {
+      return Err(PursError::AccessError(format!("Could not get the authenticated user. Status: {}, Response: {}", status, body)))
+    }
+
+    let user: octocrab::models::Author = serde_json::from_str(&body)?;
+
+    // GitHub Enterprise Server returns a 404 for the rate limit when rate limiting is disabled
+    let rate_limit =
+      octocrab
+        .ratelimit()
+        .get()
+        .await
+        .ok()
+        .map(|rate_limit| {
+          let rate = rate_limit.resources.core;
+          RateLimitStatus {
+            remaining: rate.remaining,
+            limit: rate.limit,
+            reset: chrono::DateTime::from_timestamp(rate.reset as i64, 0)
+          }
+        });
+
+    let repository_access =
+      join_all(
+        repositories
+          .iter()
+          .map(|owner_repo| get_repository_access(octocrab, owner_repo))
+      ).await;
+
+    Ok(
+      AccessReport {
+        login: user.login,
+        scopes,
+        rate_limit,
+        repositories: repositories.iter().cloned().zip(repository_access).collect()
+      }
+    )
+}
+
+async fn get_repository_access(octocrab: &Octocrab, owner_repo: &OwnerRepo) -> RepositoryAccess {
+    let OwnerRepo(owner, repo) = owner_repo;
+    match octocrab.repos(owner.0.to_owned(), repo.0.to_owned()).get().await {
+      Ok(_) => RepositoryAccess::Accessible,
+      Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404 => RepositoryAccess::NotFound,
+      Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 403 && source.message.contains("SAML") => RepositoryAccess::SsoAuthorisationRequired,
+      Err(error) => RepositoryAccess::Denied(error.to_string())
+    }
+}
+
+/// Lists the repositories of an org (or user) that could have open PRs, filtered by topic and name
+pub async fn get_org_repositories(octocrab: Octocrab, org: &OrgFilter, filters: &PrFilters) -> R<Vec<OwnerRepo>> {
+    let owner = org.owner.0.to_owned();
+    let org_repos_result =
+      octocrab
+        .orgs(owner.clone())
+        .list_repos()
+        .per_page(100)
+        .send()
+        .await;
+
+    let first_page =
+      match org_repos_result {
+        Ok(page) => page,
+        // The owner is a user and not an org
+        Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404 => {
+          octocrab
+            .users(owner)
+            .repos()
+            .per_page(100)
+            .send()
+            .await
+            .map_err(PursError::from)?
+        },
+        Err(error) => return Err(PursError::from(error))
+      };
+
+    let repositories =
+      octocrab
+        .all_pages(first_page)
+        .await
+        .map_err(PursError::from)?;
+
+    let owner_repos =
+      repositories
+        .into_iter()
+        .filter(|r| !r.archived.unwrap_or(false))
+        // Open issues include open PRs, so a repository without any can't have open PRs. Any repository can have closed or merged PRs.
+        .filter(|r| filters.state != PrStateFilter::Open || r.open_issues_count.unwrap_or(0) > 0)
+        .filter(|r| {
+          org
+            .topic
+            .as_ref()
+            .map(|topic| r.topics.iter().flatten().any(|t| t == topic))
+            .unwrap_or(true)
+        })
+        .filter(|r| {
+          org
+            .name_glob
+            .as_ref()
+            .map(|glob| glob_match(glob, &r.name))
+            .unwrap_or(true)
+        })
+        .map(|r| OwnerRepo(org.owner.clone(), Repo(r.name)))
+        .collect();
+
+    Ok(owner_repos)
+}
+
+pub async fn get_pr(octocrab: Octocrab, direct_pr: &DirectPr) -> R<PullRequest> {
+    let pull = get_pull(&octocrab, direct_pr).await?;
+
+    to_pull_request(get_pull_request_parts(&octocrab, &direct_pr.owner_repo, &pull)).await
+}
+
+async fn get_pull(octocrab: &Octocrab, direct_pr: &DirectPr) -> R<GHPullRequest> {
+    let OwnerRepo(owner, repo) = &direct_pr.owner_repo;
+    octocrab
+      .pulls(owner.0.to_owned(), repo.0.to_owned())
+      .get(direct_pr.pr_number)
+      .await
+      .map_err(PursError::from)
+}
+
+fn get_pull_request_parts(octocrab: &Octocrab, owner_repo: &OwnerRepo, pull: &octocrab::models::pulls::PullRequest) -> AsyncPullRequestParts {
+    let OwnerRepo(owner, repo) = owner_repo;
+    let pr_no = pull.number;
+    let reviews_handle = tokio::spawn(get_reviews2(octocrab.clone(), owner.clone(), repo.clone(), pr_no));
+    let comments_handle = tokio::spawn(get_comments2(octocrab.clone(), owner.clone(), repo.clone(), pr_no));
+    let diffs_handle = tokio::spawn(get_pr_diffs2(octocrab.clone(), owner.clone(), repo.clone(), pr_no));
+
+    AsyncPullRequestParts {
+        owner_repo: owner_repo.clone(),
+        pull: pull.clone(),
+        reviews_handle,
+        comments_handle,
+        diffs_handle
+    }
+}
+
+async fn to_pull_request(AsyncPullRequestParts { owner_repo, pull, reviews_handle, comments_handle, diffs_handle }: AsyncPullRequestParts) -> R<PullRequest> {
+    let res = tokio::try_join!(
+        flatten(reviews_handle),
+        flatten(comments_handle),
+        flatten(diffs_handle)
+    );
+
+    match res {
+      Ok((reviews, comments, diffs)) => {
+
+        let pr_no = pull.number;
+        let title = pull.title.clone().unwrap_or_else(|| "-".to_string());
+        let ssh_url = pull.head.repo.clone().and_then(|r| r.ssh_url);
+        let base_ssh_url = pull.base.repo.clone().and_then(|r| r.ssh_url);
+        let base_clone_url = pull.base.repo.clone().and_then(|r| r.clone_url).map(String::from);
+        let state = pull_request_state(&pull);
+        let merge_commit_sha = pull.merge_commit_sha.clone();
+        let head_sha = pull.head.sha;
+        let repo_name = pull.head.repo.clone().and_then(|r| r.full_name);
+        let branch_name = pull.head.ref_field;
+        let base_sha = pull.base.sha;
+        let config_owner_repo = owner_repo;
+        let draft = pull.draft;
+        let created_at = pull.created_at;
+        let updated_at = pull.updated_at;
+        let pr_owner = create_user(pull.user.clone().as_deref());
+
+        let pr =
+          PullRequest {
+            config_owner_repo,
+            pr_owner,
+            title,
+            pr_number: pr_no,
+            ssh_url,
+            base_ssh_url,
+            base_clone_url,
+            branch_name,
+            head_sha,
+            repo_name,
+            base_sha,
+            state,
+            merge_commit_sha,
+            reviews,
+            comments,
+            diffs,
+            draft,
+            created_at,
+            updated_at
+          };
+
+        Ok(pr)
+      },
+      Err(error) => Err(error),
+  }
+}
+
 //TODO check for unnecessary memory allocations
 pub async fn render_markdown_comments(octocrab: &Octocrab, comments: &Comments) -> R<Comments> {
   let md_start = Instant::now();
@@ -190,6 +683,7 @@ async fn get_reviews2(octocrab:  Octocrab, owner:  Owner, repo:  Repo, pr_no: u6
             .map(|u| u.login);
 
         let comment = r.body;
+        let commit_id = r.commit_id;
         let state = match r.state {
           Some(GHReviewState::Approved)         => ReviewState::Approved,
           Some(GHReviewState::Pending)          => ReviewState::Pending,
@@ -202,6 +696,7 @@ async fn get_reviews2(octocrab:  Octocrab, owner:  Owner, repo:  Repo, pr_no: u6
         Review {
             user,
             comment,
+            commit_id,
             state
         }
    }).collect::<Vec<_>>();
@@ -251,53 +746,24 @@ async fn get_comments2(octocrab: Octocrab, owner: Owner, repo: Repo, pr_no: u64)
 }
 
 
-async fn get_pr_diffs2(octocrab: Octocrab, owner: Owner, repo: Repo, pr_no: u64) -> R<PullRequestDiff> {
-    let diff_string =
+/// The GitHub API rejects diffs of very large PRs. Rather than failing to load the PR, its diff is then generated from the checkout.
+async fn get_pr_diffs2(octocrab: Octocrab, owner: Owner, repo: Repo, pr_no: u64) -> R<Option<PullRequestDiff>> {
+    let diff_result =
         octocrab
         .pulls(owner.0.to_owned(), repo.0.to_owned())
         .get_diff(pr_no)
-        .await?;
+        .await;
 
-    parse_diffs(&diff_string)
+    match diff_result {
+      Ok(diff_string) => parse_diffs(&diff_string).map(Some),
+      Err(octocrab::Error::GitHub { source, .. }) if is_diff_too_large(source.status_code.as_u16(), &source.message) => Ok(None),
+      Err(error) => Err(PursError::from(error))
+    }
 }
 
-fn parse_diffs(diff: &str) -> R<PullRequestDiff> {
-  let mut patch = PatchSet::new();
-  let parse_result = patch.parse(diff).map_err(PursError::from);
-
-  parse_result.map(|_| {
-      let diffs = patch.files().iter().map (|p| {
-          let file_name =
-              // if a file is deleted there is no target file (because it's deleted)
-              // if a file is added there is no source file (because it's a new file)
-              // if a file is modified there is both a source and target file
-              if p.is_removed_file() {
-                  parse_only_file_name(&p.source_file)
-              } else {
-                  parse_only_file_name(&p.target_file)
-              };
-
-          let contents = p.to_string();
-
-          GitDiff {
-              file_name,
-              contents
-          }
-      }).collect();
-
-      PullRequestDiff(diffs)
-  })
-}
-
-fn parse_only_file_name(diff_file: &str) -> String {
-    let mut file_name = diff_file.to_string();
-
-    // TODO: If this fails the format of the file name is not what we expected
-    // Return a specific error later
-    let index = file_name.find('/').unwrap() + 1;
-    // Remove prefix of a/.. or b/..
-    file_name.replace_range(..index, "");
-    file_name
+/// GitHub responds with a 406, or a 422 on some endpoints, when a diff exceeds its maximum number of files or lines
+fn is_diff_too_large(status_code: u16, message: &str) -> bool {
+    status_code == 406 || (status_code == 422 && message.to_lowercase().contains("diff exceeded"))
 }
 
 fn create_user(user: Option<&octocrab::models::Author>) -> Option<User> {
@@ -311,17 +777,103 @@ async fn flatten<T>(handle: tokio::task::JoinHandle<R<T>>) -> R<T> {
     }
 }
 
-async fn get_pulls(octocrab: Octocrab, owner_repo: OwnerRepo) -> R<octocrab::Page<octocrab::models::pulls::PullRequest>> {
+/// Checks the filters that the pulls API can't apply. The other filters use the search API.
+pub fn matches_filters(pull: &octocrab::models::pulls::PullRequest, filters: &PrFilters) -> bool {
+    let state_matches = filters.state.matches(&pull_request_state(pull));
+
+    let since_matches =
+      filters
+        .since
+        .map(|since| {
+          pull
+            .updated_at
+            .map(|updated_at| Utc::now().signed_duration_since(updated_at) <= since)
+            .unwrap_or(false)
+        })
+        .unwrap_or(true);
+
+    state_matches && since_matches
+}
+
+/// The search query for the PRs of a repository that match the filters
+fn repository_search_query(owner_repo: &OwnerRepo, filters: &PrFilters) -> String {
+    let state =
+      match filters.state {
+        PrStateFilter::Open => Some("is:open"),
+        PrStateFilter::Closed => Some("is:closed"),
+        PrStateFilter::Merged => Some("is:merged"),
+        PrStateFilter::All => None,
+      };
+
+    ["is:pr".to_owned(), format!("repo:{}", owner_repo)]
+      .into_iter()
+      .chain(state.map(|s| s.to_owned()))
+      .chain(filter_qualifiers(filters))
+      .collect::<Vec<_>>()
+      .join(" ")
+}
+
+/// The search qualifiers for the filters, except for the state
+fn filter_qualifiers(filters: &PrFilters) -> Vec<String> {
+    let author = filters.author.as_ref().map(|author| format!("author:{}", author));
+    let labels = filters.labels.iter().map(|label| format!("label:\"{}\"", label));
+    let base = filters.base.as_ref().map(|base| format!("base:{}", base));
+    let draft = filters.exclude_drafts.then(|| "draft:false".to_owned());
+    let review_requested = filters.review_requested.as_ref().map(|reviewer| format!("review-requested:{}", reviewer));
+    let since =
+      filters
+        .since
+        .map(|since| format!("updated:>={}", (Utc::now() - since).format("%Y-%m-%dT%H:%M:%SZ")));
+
+    author
+      .into_iter()
+      .chain(labels)
+      .chain(base)
+      .chain(draft)
+      .chain(review_requested)
+      .chain(since)
+      .collect()
+}
+
+fn pull_request_state(pull: &octocrab::models::pulls::PullRequest) -> PullRequestState {
+    match (&pull.merged_at, &pull.state) {
+      (Some(_), _) => PullRequestState::Merged,
+      (None, Some(IssueState::Closed)) => PullRequestState::Closed,
+      _ => PullRequestState::Open
+    }
+}
+
+async fn get_pulls(octocrab: Octocrab, owner_repo: OwnerRepo, filters: PrFilters, limit: PrLimit) -> R<PullsPage> {
+    if filters.needs_search() {
+      let sort = if sorts_by_updated(&filters) { "updated" } else { "created" };
+      return search_issues(&octocrab, &repository_search_query(&owner_repo, &filters), sort, limit).await.map(|page| PullsPage::Search(page.into()))
+    }
+
     let OwnerRepo(owner, repo) = owner_repo;
-    octocrab
-      .pulls(owner.0.to_owned(), repo.0.to_owned())
-      .list()
-      .state(params::State::Open)
-      .sort(params::pulls::Sort::Created)
+    let sort = if sorts_by_updated(&filters) { params::pulls::Sort::Updated } else { params::pulls::Sort::Created };
+    let pulls = octocrab.pulls(owner.0.to_owned(), repo.0.to_owned());
+    let list_builder =
+      match filters.base {
+        Some(base_branch) => pulls.list().base(base_branch),
+        None => pulls.list()
+      };
+
+    // GitHub doesn't have a merged state, so we filter closed PRs for merged PRs
+    let state =
+      match filters.state {
+        PrStateFilter::Open => params::State::Open,
+        PrStateFilter::Closed | PrStateFilter::Merged => params::State::Closed,
+        PrStateFilter::All => params::State::All,
+      };
+
+    list_builder
+      .state(state)
+      .sort(sort)
       .direction(params::Direction::Descending)
-      .per_page(20)
+      .per_page(limit.value().min(MAX_PER_PAGE) as u8)
       .send()
       .await
+      .map(|page| PullsPage::List(page.into()))
       .map_err( PursError::from)
 }
 
@@ -339,3 +891,8 @@ fn flatten_results<T, E, E2, F>(nested_results: Result<Result<T, E>, E2>, f: F)
 {
   nested_results.map_err(f).and_then(std::convert::identity)
 }
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,703 @@
+use wiremock::{MockServer, Mock, ResponseTemplate};
+use wiremock::matchers::{method, path, query_param};
+use octocrab::{Octocrab, OctocrabBuilder};
+use crate::github::{get_org_repositories, get_pulls, matches_filters, repository_search_query, take_pulls, PullsCursor, PullsPage, PageCursor, MAX_PAGES_PER_FETCH, search_pull_requests, render_markdown, get_access_report, get_pr_diffs2};
+use crate::model::*;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+fn repository(id: u64, name: &str, open_issues_count: u32, archived: bool, topics: Vec<&str>) -> serde_json::Value {
+  json!({
+    "id": id,
+    "name": name,
+    "url": format!("https://api.github.com/repos/acme/{}", name),
+    "open_issues_count": open_issues_count,
+    "archived": archived,
+    "topics": topics
+  })
+}
+
+fn octocrab(mock_server: &MockServer) -> Octocrab {
+  OctocrabBuilder::new()
+    .base_uri(mock_server.uri())
+    .unwrap()
+    .build()
+    .unwrap()
+}
+
+fn owner_repo(repo: &str) -> OwnerRepo {
+  OwnerRepo(Owner("acme".to_owned()), Repo(repo.to_owned()))
+}
+
+#[tokio::test]
+async fn gets_org_repositories_that_could_have_open_prs() {
+  let mock_server = MockServer::start().await;
+  let repositories =
+    json!([
+      repository(1, "service-payments", 3, false, vec!["backend"]),
+      repository(2, "service-billing", 0, false, vec!["backend"]),
+      repository(3, "service-legacy", 5, true, vec!["backend"]),
+      repository(4, "website", 2, false, vec!["frontend"]),
+    ]);
+
+  Mock::given(method("GET"))
+    .and(path("/orgs/acme/repos"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(repositories))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+
+  let all = OrgFilter { owner: Owner("acme".to_owned()), topic: None, name_glob: None };
+  let result = get_org_repositories(octocrab.clone(), &all, &PrFilters::default()).await.unwrap();
+  assert_eq!(result, vec![owner_repo("service-payments"), owner_repo("website")]);
+
+  let by_topic = OrgFilter { owner: Owner("acme".to_owned()), topic: Some("frontend".to_owned()), name_glob: None };
+  let result = get_org_repositories(octocrab.clone(), &by_topic, &PrFilters::default()).await.unwrap();
+  assert_eq!(result, vec![owner_repo("website")]);
+
+  let by_name = OrgFilter { owner: Owner("acme".to_owned()), topic: None, name_glob: Some("service-*".to_owned()) };
+  let result = get_org_repositories(octocrab, &by_name, &PrFilters::default()).await.unwrap();
+  assert_eq!(result, vec![owner_repo("service-payments")]);
+}
+
+#[tokio::test]
+async fn gets_org_repositories_without_open_issues_for_closed_or_merged_prs() {
+  let mock_server = MockServer::start().await;
+  let repositories =
+    json!([
+      repository(1, "service-payments", 3, false, vec!["backend"]),
+      repository(2, "service-billing", 0, false, vec!["backend"]),
+      repository(3, "service-legacy", 0, true, vec!["backend"]),
+    ]);
+
+  Mock::given(method("GET"))
+    .and(path("/orgs/acme/repos"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(repositories))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let org = OrgFilter { owner: Owner("acme".to_owned()), topic: None, name_glob: None };
+
+  for state in [PrStateFilter::Merged, PrStateFilter::Closed, PrStateFilter::All] {
+    let filters = PrFilters { state, ..PrFilters::default() };
+    let result = get_org_repositories(octocrab.clone(), &org, &filters).await.unwrap();
+    assert_eq!(result, vec![owner_repo("service-payments"), owner_repo("service-billing")]);
+  }
+}
+
+#[tokio::test]
+async fn gets_user_repositories_when_owner_is_not_an_org() {
+  let mock_server = MockServer::start().await;
+
+  Mock::given(method("GET"))
+    .and(path("/orgs/acme/repos"))
+    .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "message": "Not Found" })))
+    .mount(&mock_server)
+    .await;
+
+  Mock::given(method("GET"))
+    .and(path("/users/acme/repos"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!([repository(1, "dotfiles", 1, false, vec![])])))
+    .mount(&mock_server)
+    .await;
+
+  let org = OrgFilter { owner: Owner("acme".to_owned()), topic: None, name_glob: None };
+  let result = get_org_repositories(octocrab(&mock_server), &org, &PrFilters::default()).await.unwrap();
+
+  assert_eq!(result, vec![owner_repo("dotfiles")]);
+}
+
+fn author(login: &str) -> serde_json::Value {
+  let url = format!("https://api.github.com/users/{}", login);
+  json!({
+    "login": login,
+    "id": 1,
+    "node_id": "node",
+    "avatar_url": format!("https://avatars.githubusercontent.com/{}", login),
+    "gravatar_id": "",
+    "url": url,
+    "html_url": format!("https://github.com/{}", login),
+    "followers_url": url,
+    "following_url": url,
+    "gists_url": url,
+    "starred_url": url,
+    "subscriptions_url": url,
+    "organizations_url": url,
+    "repos_url": url,
+    "events_url": url,
+    "received_events_url": url,
+    "type": "User",
+    "site_admin": false
+  })
+}
+
+fn label(name: &str) -> serde_json::Value {
+  json!({
+    "id": 1,
+    "node_id": "node",
+    "url": format!("https://api.github.com/labels/{}", name),
+    "name": name,
+    "color": "ffffff",
+    "default": false
+  })
+}
+
+fn pull(user: &str, labels: Vec<&str>, draft: bool, requested_reviewers: Vec<&str>) -> octocrab::models::pulls::PullRequest {
+  pull_with_state(user, labels, draft, requested_reviewers, "open", None, Some(chrono::Utc::now()))
+}
+
+fn pull_with_state(user: &str, labels: Vec<&str>, draft: bool, requested_reviewers: Vec<&str>, state: &str, merged_at: Option<chrono::DateTime<chrono::Utc>>, updated_at: Option<chrono::DateTime<chrono::Utc>>) -> octocrab::models::pulls::PullRequest {
+  let branch = |name: &str| json!({ "ref": name, "sha": "abc" });
+  let pull_json =
+    json!({
+      "state": state,
+      "merged_at": merged_at,
+      "updated_at": updated_at,
+      "url": "https://api.github.com/repos/acme/service/pulls/1",
+      "id": 1,
+      "number": 1,
+      "locked": false,
+      "maintainer_can_modify": false,
+      "head": branch("feature"),
+      "base": branch("main"),
+      "user": author(user),
+      "labels": labels.into_iter().map(label).collect::<Vec<_>>(),
+      "draft": draft,
+      "requested_reviewers": requested_reviewers.into_iter().map(author).collect::<Vec<_>>()
+    });
+
+  serde_json::from_value(pull_json).unwrap()
+}
+
+#[test]
+fn matches_all_pulls_without_filters() {
+  let pull = pull("user1", vec![], true, vec![]);
+  assert!(matches_filters(&pull, &PrFilters::default()))
+}
+
+#[test]
+fn searches_for_pulls_by_author_label_draft_and_requested_reviewer() {
+  let filters =
+    PrFilters {
+      author: Some(UserFilter::new("@me")),
+      labels: vec!["bug".to_owned(), "needs review".to_owned()],
+      base: Some("main".to_owned()),
+      exclude_drafts: true,
+      review_requested: Some(UserFilter::new("reviewer1")),
+      ..PrFilters::default()
+    };
+
+  assert!(filters.needs_search());
+  assert_eq!(
+    repository_search_query(&owner_repo("service"), &filters),
+    "is:pr repo:acme/service is:open author:@me label:\"bug\" label:\"needs review\" base:main draft:false review-requested:reviewer1"
+  );
+}
+
+#[test]
+fn searches_for_pulls_by_state() {
+  let filters = |state: PrStateFilter| PrFilters { state, exclude_drafts: true, ..PrFilters::default() };
+
+  assert_eq!(repository_search_query(&owner_repo("service"), &filters(PrStateFilter::Merged)), "is:pr repo:acme/service is:merged draft:false");
+  assert_eq!(repository_search_query(&owner_repo("service"), &filters(PrStateFilter::All)), "is:pr repo:acme/service draft:false");
+}
+
+#[test]
+fn lists_pulls_without_search_filters() {
+  let filters = PrFilters { base: Some("main".to_owned()), state: PrStateFilter::Merged, since: Some(chrono::Duration::days(1)), ..PrFilters::default() };
+  assert!(!filters.needs_search())
+}
+
+#[test]
+fn matches_pulls_by_state() {
+  let now = Some(chrono::Utc::now());
+  let open = pull_with_state("user1", vec![], false, vec![], "open", None, now);
+  let closed = pull_with_state("user1", vec![], false, vec![], "closed", None, now);
+  let merged = pull_with_state("user1", vec![], false, vec![], "closed", now, now);
+
+  let filters = |state: PrStateFilter| PrFilters { state, ..PrFilters::default() };
+
+  assert!(matches_filters(&open, &filters(PrStateFilter::Open)));
+  assert!(!matches_filters(&closed, &filters(PrStateFilter::Open)));
+
+  assert!(matches_filters(&closed, &filters(PrStateFilter::Closed)));
+  assert!(matches_filters(&merged, &filters(PrStateFilter::Closed)));
+
+  assert!(!matches_filters(&closed, &filters(PrStateFilter::Merged)));
+  assert!(matches_filters(&merged, &filters(PrStateFilter::Merged)));
+
+  assert!(matches_filters(&open, &filters(PrStateFilter::All)));
+  assert!(matches_filters(&merged, &filters(PrStateFilter::All)));
+}
+
+#[test]
+fn matches_pulls_updated_since() {
+  let recent = pull_with_state("user1", vec![], false, vec![], "open", None, Some(chrono::Utc::now() - chrono::Duration::hours(2)));
+  let old = pull_with_state("user1", vec![], false, vec![], "open", None, Some(chrono::Utc::now() - chrono::Duration::days(2)));
+
+  let filters = PrFilters { since: Some(chrono::Duration::hours(12)), ..PrFilters::default() };
+
+  assert!(matches_filters(&recent, &filters));
+  assert!(!matches_filters(&old, &filters));
+}
+
+fn logins(pulls: &[octocrab::models::pulls::PullRequest]) -> Vec<String> {
+  pulls
+    .iter()
+    .filter_map(|p| p.user.as_ref().map(|u| u.login.clone()))
+    .collect()
+}
+
+fn cursor(items: Vec<octocrab::models::pulls::PullRequest>, next: Option<String>) -> PullsCursor {
+  let mut page = octocrab::Page::default();
+  page.items = items;
+  page.next = next.map(|n| n.parse().unwrap());
+
+  PullsCursor {
+    owner_repo: owner_repo("service"),
+    page: PullsPage::List(PageCursor::from(page))
+  }
+}
+
+#[tokio::test]
+async fn takes_pulls_across_pages_up_to_the_limit() {
+  let mock_server = MockServer::start().await;
+
+  let next_page = json!([
+    serde_json::to_value(pull("user3", vec![], false, vec![])).unwrap(),
+    serde_json::to_value(pull("user4", vec![], false, vec![])).unwrap(),
+  ]);
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/service/pulls"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(next_page))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let limit = PrLimit::new(3).unwrap();
+  let first_page = vec![pull("user1", vec![], false, vec![]), pull("user2", vec![], false, vec![])];
+  let next = format!("{}/repos/acme/service/pulls?page=2", mock_server.uri());
+
+  let (pulls, cursor) = take_pulls(octocrab.clone(), cursor(first_page, Some(next)), PrFilters::default(), limit).await.unwrap();
+  assert_eq!(logins(&pulls), vec!["user1", "user2", "user3"]);
+  assert!(cursor.has_more());
+
+  let (pulls, cursor) = take_pulls(octocrab, cursor, PrFilters::default(), limit).await.unwrap();
+  assert_eq!(logins(&pulls), vec!["user4"]);
+  assert!(!cursor.has_more());
+}
+
+#[tokio::test]
+async fn takes_only_matching_pulls() {
+  let mock_server = MockServer::start().await;
+  let octocrab = octocrab(&mock_server);
+
+  let now = Some(chrono::Utc::now());
+  let pulls = vec![
+    pull_with_state("user1", vec![], false, vec![], "closed", now, now),
+    pull_with_state("user2", vec![], false, vec![], "closed", None, now),
+    pull_with_state("user3", vec![], false, vec![], "closed", now, now),
+  ];
+  let filters = PrFilters { state: PrStateFilter::Merged, ..PrFilters::default() };
+
+  let (pulls, cursor) = take_pulls(octocrab, cursor(pulls, None), filters, PrLimit::default()).await.unwrap();
+  assert_eq!(logins(&pulls), vec!["user1", "user3"]);
+  assert!(!cursor.has_more());
+}
+
+#[tokio::test]
+async fn stops_taking_pulls_updated_before_since() {
+  let mock_server = MockServer::start().await;
+  let octocrab = octocrab(&mock_server);
+
+  let recently = Some(chrono::Utc::now() - chrono::Duration::hours(1));
+  let long_ago = Some(chrono::Utc::now() - chrono::Duration::days(7));
+  let pulls = vec![
+    pull_with_state("user1", vec![], false, vec![], "closed", None, recently),
+    pull_with_state("user2", vec![], false, vec![], "closed", None, long_ago),
+    pull_with_state("user3", vec![], false, vec![], "closed", None, recently),
+  ];
+  let filters = PrFilters { state: PrStateFilter::Closed, since: Some(chrono::Duration::days(1)), ..PrFilters::default() };
+  let next = Some(format!("{}/repos/acme/service/pulls?page=2", mock_server.uri()));
+
+  let (pulls, cursor) = take_pulls(octocrab, cursor(pulls, next), filters, PrLimit::default()).await.unwrap();
+  assert_eq!(logins(&pulls), vec!["user1"]);
+  assert!(!cursor.has_more());
+}
+
+#[tokio::test]
+async fn stops_following_pages_after_the_maximum_pages_per_fetch() {
+  let mock_server = MockServer::start().await;
+
+  let closed_pulls = json!([serde_json::to_value(pull_with_state("user1", vec![], false, vec![], "closed", None, Some(chrono::Utc::now()))).unwrap()]);
+  let next = format!("{}/repos/acme/service/pulls?page=2", mock_server.uri());
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/service/pulls"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(closed_pulls).insert_header("link", format!("<{}>; rel=\"next\"", next).as_str()))
+    .expect(MAX_PAGES_PER_FETCH as u64)
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let filters = PrFilters { state: PrStateFilter::Merged, ..PrFilters::default() };
+
+  let (pulls, cursor) = take_pulls(octocrab, cursor(vec![], Some(next)), filters, PrLimit::default()).await.unwrap();
+  assert!(pulls.is_empty());
+  assert!(cursor.has_more());
+}
+
+#[tokio::test]
+async fn lists_open_pulls_by_when_they_were_updated_since() {
+  let mock_server = MockServer::start().await;
+
+  let recently = Some(chrono::Utc::now() - chrono::Duration::hours(1));
+  let long_ago = Some(chrono::Utc::now() - chrono::Duration::days(7));
+  let pulls = json!([
+    serde_json::to_value(pull_with_state("user1", vec![], false, vec![], "open", None, recently)).unwrap(),
+    serde_json::to_value(pull_with_state("user2", vec![], false, vec![], "open", None, long_ago)).unwrap(),
+  ]);
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/service/pulls"))
+    .and(query_param("state", "open"))
+    .and(query_param("sort", "updated"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(pulls))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let filters = PrFilters { since: Some(chrono::Duration::days(1)), ..PrFilters::default() };
+  let page = get_pulls(octocrab.clone(), owner_repo("service"), filters.clone(), PrLimit::default()).await.unwrap();
+  let cursor = PullsCursor { owner_repo: owner_repo("service"), page };
+
+  let (pulls, cursor) = take_pulls(octocrab, cursor, filters, PrLimit::default()).await.unwrap();
+  assert_eq!(logins(&pulls), vec!["user1"]);
+  assert!(!cursor.has_more());
+}
+
+fn issue(repository_url: &str, number: u64, is_pull_request: bool) -> serde_json::Value {
+  let url = format!("{}/issues/{}", repository_url, number);
+  let pull_request_url = format!("{}/pulls/{}", repository_url, number);
+  let pull_request =
+    is_pull_request.then(|| {
+      json!({
+        "url": pull_request_url,
+        "html_url": pull_request_url,
+        "diff_url": pull_request_url,
+        "patch_url": pull_request_url
+      })
+    });
+
+  json!({
+    "id": number,
+    "node_id": "node",
+    "url": url,
+    "repository_url": repository_url,
+    "labels_url": url,
+    "comments_url": url,
+    "events_url": url,
+    "html_url": url,
+    "number": number,
+    "state": "open",
+    "state_reason": null,
+    "title": "Title",
+    "body": null,
+    "user": author("user1"),
+    "labels": [],
+    "assignees": [],
+    "author_association": "MEMBER",
+    "locked": false,
+    "comments": 0,
+    "pull_request": pull_request,
+    "created_at": "2024-01-01T00:00:00Z",
+    "updated_at": "2024-01-01T00:00:00Z"
+  })
+}
+
+#[tokio::test]
+async fn searches_pull_requests_across_repositories() {
+  let mock_server = MockServer::start().await;
+  let results =
+    json!({
+      "total_count": 3,
+      "incomplete_results": false,
+      "items": [
+        issue("https://api.github.com/repos/acme/service", 12, true),
+        issue("https://api.github.com/repos/acme/service", 13, false),
+        issue("https://ghes.acme.com/api/v3/repos/other/website", 7, true),
+      ]
+    });
+
+  Mock::given(method("GET"))
+    .and(path("/search/issues"))
+    .and(query_param("q", "is:pr is:open review-requested:@me"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(results))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let result = search_pull_requests(&octocrab, "is:pr is:open review-requested:@me", PrLimit::default()).await.unwrap();
+
+  let expected =
+    vec![
+      DirectPr { owner_repo: owner_repo("service"), pr_number: 12 },
+      DirectPr { owner_repo: OwnerRepo(Owner("other".to_owned()), Repo("website".to_owned())), pr_number: 7 },
+    ];
+
+  assert_eq!(result, expected);
+}
+
+#[tokio::test]
+async fn takes_pulls_found_by_searching_the_repository() {
+  let mock_server = MockServer::start().await;
+  let repository_url = format!("{}/repos/acme/service", mock_server.uri());
+  let results =
+    json!({
+      "total_count": 1,
+      "incomplete_results": false,
+      "items": [issue(&repository_url, 1, true)]
+    });
+
+  Mock::given(method("GET"))
+    .and(path("/search/issues"))
+    .and(query_param("q", "is:pr repo:acme/service is:open label:\"bug\""))
+    .respond_with(ResponseTemplate::new(200).set_body_json(results))
+    .mount(&mock_server)
+    .await;
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/service/pulls/1"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(pull("user1", vec!["bug"], false, vec![])))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let filters = PrFilters { labels: vec!["bug".to_owned()], ..PrFilters::default() };
+  let page = get_pulls(octocrab.clone(), owner_repo("service"), filters.clone(), PrLimit::default()).await.unwrap();
+  let cursor = PullsCursor { owner_repo: owner_repo("service"), page };
+
+  let (pulls, cursor) = take_pulls(octocrab, cursor, filters, PrLimit::default()).await.unwrap();
+  assert_eq!(logins(&pulls), vec!["user1"]);
+  assert!(!cursor.has_more());
+}
+
+#[tokio::test]
+async fn renders_markdown_through_an_enterprise_api_url() {
+  let mock_server = MockServer::start().await;
+
+  Mock::given(method("POST"))
+    .and(path("/api/v3/markdown/raw"))
+    .respond_with(ResponseTemplate::new(200).set_body_string("<p><strong>LGTM</strong></p>"))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab =
+    OctocrabBuilder::new()
+      .base_uri(format!("{}/api/v3", mock_server.uri()))
+      .unwrap()
+      .build()
+      .unwrap();
+
+  let result = render_markdown(octocrab, "**LGTM**".to_owned()).await.unwrap();
+  assert_eq!(result, "<p><strong>LGTM</strong></p>");
+}
+
+fn rate_limit(remaining: usize) -> serde_json::Value {
+  let rate = json!({ "limit": 5000, "used": 5000 - remaining, "remaining": remaining, "reset": 1704067200 });
+  json!({
+    "resources": { "core": rate, "search": rate },
+    "rate": rate
+  })
+}
+
+async fn mount_user_and_rate_limit(mock_server: &MockServer, scopes: &str, remaining: usize) {
+  Mock::given(method("GET"))
+    .and(path("/user"))
+    .respond_with(ResponseTemplate::new(200).insert_header("x-oauth-scopes", scopes).set_body_json(author("user1")))
+    .mount(mock_server)
+    .await;
+
+  Mock::given(method("GET"))
+    .and(path("/rate_limit"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(rate_limit(remaining)))
+    .mount(mock_server)
+    .await;
+}
+
+#[tokio::test]
+async fn reports_access_to_repositories() {
+  let mock_server = MockServer::start().await;
+  mount_user_and_rate_limit(&mock_server, "repo, read:org", 4999).await;
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/service"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(repository(1, "service", 1, false, vec![])))
+    .mount(&mock_server)
+    .await;
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/sevrice"))
+    .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "message": "Not Found" })))
+    .mount(&mock_server)
+    .await;
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/private"))
+    .respond_with(ResponseTemplate::new(403).set_body_json(json!({ "message": "Resource protected by organization SAML enforcement. You must grant your Personal Access token access to this organization." })))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let repositories = vec![owner_repo("service"), owner_repo("sevrice"), owner_repo("private")];
+  let report = get_access_report(&octocrab, &repositories).await.unwrap();
+
+  assert_eq!(report.login, "user1");
+  assert_eq!(report.scopes, Some(vec!["repo".to_owned(), "read:org".to_owned()]));
+  assert_eq!(report.rate_limit.as_ref().map(|r| r.remaining), Some(4999));
+  assert_eq!(
+    report.repositories,
+    vec![
+      (owner_repo("service"), RepositoryAccess::Accessible),
+      (owner_repo("sevrice"), RepositoryAccess::NotFound),
+      (owner_repo("private"), RepositoryAccess::SsoAuthorisationRequired),
+    ]
+  );
+
+  let error = report.validate().unwrap_err().to_string();
+  assert!(error.contains("acme/sevrice: not found"));
+  assert!(error.contains("acme/private: the token needs to be authorised for SSO"));
+  assert!(!error.contains("acme/service"));
+}
+
+#[tokio::test]
+async fn rejects_tokens_without_repo_scope_or_rate_limit() {
+  let mock_server = MockServer::start().await;
+  mount_user_and_rate_limit(&mock_server, "read:org", 0).await;
+
+  let octocrab = octocrab(&mock_server);
+  let report = get_access_report(&octocrab, &[]).await.unwrap();
+
+  let error = report.validate().unwrap_err().to_string();
+  assert!(error.contains("does not have the repo scope"));
+  assert!(error.contains("rate limit has been exceeded"));
+}
+
+#[tokio::test]
+async fn reports_a_missing_rate_limit_without_failing() {
+  let mock_server = MockServer::start().await;
+
+  Mock::given(method("GET"))
+    .and(path("/user"))
+    .respond_with(ResponseTemplate::new(200).insert_header("x-oauth-scopes", "repo").set_body_json(author("user1")))
+    .mount(&mock_server)
+    .await;
+
+  Mock::given(method("GET"))
+    .and(path("/rate_limit"))
+    .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "message": "Rate limiting is not enabled." })))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let report = get_access_report(&octocrab, &[]).await.unwrap();
+
+  assert_eq!(report.rate_limit, None);
+  assert!(report.to_string().contains("Rate limit: not reported"));
+  assert!(report.validate().is_ok());
+}
+
+#[tokio::test]
+async fn rejects_invalid_tokens() {
+  let mock_server = MockServer::start().await;
+
+  Mock::given(method("GET"))
+    .and(path("/user"))
+    .respond_with(ResponseTemplate::new(401).set_body_json(json!({ "message": "Bad credentials" })))
+    .mount(&mock_server)
+    .await;
+
+  let octocrab = octocrab(&mock_server);
+  let result = get_access_report(&octocrab, &[owner_repo("service")]).await;
+
+  assert!(matches!(result, Err(PursError::AccessError(_))));
+}
+
+#[tokio::test]
+async fn gets_pr_diffs() {
+  let mock_server = MockServer::start().await;
+  let diff = "diff --git a/README.md b/README.md\nindex 1111111..2222222 100644\n--- a/README.md\n+++ b/README.md\n@@ -1 +1 @@\n-readme\n+updated readme\n";
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/website/pulls/7"))
+    .respond_with(ResponseTemplate::new(200).set_body_string(diff))
+    .mount(&mock_server)
+    .await;
+
+  let result = get_pr_diffs2(octocrab(&mock_server), Owner("acme".to_owned()), Repo("website".to_owned()), 7).await.unwrap();
+
+  let file_names: Vec<String> = result.unwrap().0.into_iter().map(|d| d.file_name).collect();
+  assert_eq!(file_names, vec!["README.md".to_owned()]);
+}
+
+#[tokio::test]
+async fn has_no_pr_diffs_when_diff_is_too_large() {
+  let mock_server = MockServer::start().await;
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/website/pulls/7"))
+    .respond_with(
+      ResponseTemplate::new(406)
+        .set_body_json(json!({ "message": "Sorry, the diff exceeded the maximum number of files (300)." }))
+    )
+    .mount(&mock_server)
+    .await;
+
+  let result = get_pr_diffs2(octocrab(&mock_server), Owner("acme".to_owned()), Repo("website".to_owned()), 7).await;
+
+  assert!(matches!(result, Ok(None)));
+}
+
+#[tokio::test]
+async fn has_no_pr_diffs_when_diff_exceeds_the_maximum_lines() {
+  let mock_server = MockServer::start().await;
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/website/pulls/7"))
+    .respond_with(
+      ResponseTemplate::new(422)
+        .set_body_json(json!({ "message": "Server Error: Sorry, this diff is unavailable. The diff exceeded the maximum number of lines (20000)." }))
+    )
+    .mount(&mock_server)
+    .await;
+
+  let result = get_pr_diffs2(octocrab(&mock_server), Owner("acme".to_owned()), Repo("website".to_owned()), 7).await;
+
+  assert!(matches!(result, Ok(None)));
+}
+
+#[tokio::test]
+async fn fails_to_get_pr_diffs_on_other_errors() {
+  let mock_server = MockServer::start().await;
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/website/pulls/7"))
+    .respond_with(ResponseTemplate::new(422).set_body_json(json!({ "message": "Validation Failed" })))
+    .mount(&mock_server)
+    .await;
+
+  Mock::given(method("GET"))
+    .and(path("/repos/acme/website/pulls/8"))
+    .respond_with(ResponseTemplate::new(500).set_body_json(json!({ "message": "Server Error" })))
+    .mount(&mock_server)
+    .await;
+
+  let validation_result = get_pr_diffs2(octocrab(&mock_server), Owner("acme".to_owned()), Repo("website".to_owned()), 7).await;
+  let server_error_result = get_pr_diffs2(octocrab(&mock_server), Owner("acme".to_owned()), Repo("website".to_owned()), 8).await;
+
+  assert!(matches!(validation_result, Err(PursError::Octocrab(_))));
+  assert!(matches!(server_error_result, Err(PursError::Octocrab(_))));
+}
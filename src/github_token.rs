@@ -0,0 +1,152 @@
+use crate::model::*;
+use dirs::home_dir;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const GH_HOSTS_FILE: &str = "hosts.yml";
+
+/// Finds a GitHub token from, in order: the command line (or GH_ACCESS_TOKEN), the gh CLI's hosts.yml, the git credential helper
+/// and the token_file in the config file.
+pub fn resolve_token(cli_token: Option<&str>, host: &str, token_file: Option<&Path>) -> Result<(GitHubToken, TokenSource), CommandLineArgumentFailure> {
+  if let Some(token) = cli_token {
+    return Ok((GitHubToken::new(token), TokenSource::CommandLine))
+  }
+
+  let gh_hosts_file = get_gh_hosts_file();
+  let gh_token =
+    gh_hosts_file
+      .as_ref()
+      .and_then(|hosts_file| fs::read_to_string(hosts_file).ok())
+      .and_then(|contents| parse_gh_hosts(&contents, host));
+
+  if let (Some(token), Some(hosts_file)) = (gh_token, &gh_hosts_file) {
+    return Ok((GitHubToken::new(&token), TokenSource::GhCli(hosts_file.clone())))
+  }
+
+  if let Some(token) = git_credential_fill(host) {
+    return Ok((GitHubToken::new(&token), TokenSource::GitCredentialHelper(host.to_owned())))
+  }
+
+  match token_file {
+    Some(token_file) => {
+      read_token_file(token_file).map(|token| (token, TokenSource::TokenFile(token_file.to_path_buf())))
+    },
+    None => {
+      let gh_hosts_file_name =
+        gh_hosts_file
+          .map(|f| f.to_string_lossy().to_string())
+          .unwrap_or_else(|| "-".to_owned());
+
+      let error =
+        format!(
+          "Could not find a GitHub Personal Access Token. Tried:\n\
+           \t--token and the GH_ACCESS_TOKEN environment variable: not supplied\n\
+           \tgh CLI config: no token for {} in {}\n\
+           \tgit credential helper: no credentials for https://{}\n\
+           \ttoken_file in the config file: not set",
+          host,
+          gh_hosts_file_name,
+          host
+        );
+
+      Err(CommandLineArgumentFailure::new(&error))
+    }
+  }
+}
+
+pub fn read_token_file(token_file: &Path) -> Result<GitHubToken, CommandLineArgumentFailure> {
+  let token_file_name = token_file.to_string_lossy();
+  let contents =
+    fs::read_to_string(token_file)
+      .map_err(|e| CommandLineArgumentFailure::new(&format!("Could not read token_file: {}. Error: {}", token_file_name, e)))?;
+
+  match contents.trim() {
+    "" => Err(CommandLineArgumentFailure::new(&format!("token_file is empty: {}", token_file_name))),
+    token => Ok(GitHubToken::new(token))
+  }
+}
+
+/// Finds the oauth_token of a host in the gh CLI's hosts.yml. Newer versions of gh may store the token in the system keyring
+/// instead, in which case there is no token in the file.
+pub fn parse_gh_hosts(contents: &str, host: &str) -> Option<String> {
+  let mut in_host = false;
+
+  for line in contents.lines() {
+    let trimmed_line = line.trim();
+    if trimmed_line.is_empty() || trimmed_line.starts_with('#') {
+      continue;
+    }
+
+    let is_top_level = !line.starts_with([' ', '\t']);
+    if is_top_level {
+      in_host = trimmed_line.trim_end_matches(':').trim_matches(['"', '\'']) == host;
+    } else if in_host {
+      let token =
+        trimmed_line
+          .strip_prefix("oauth_token:")
+          .map(|value| value.trim().trim_matches(['"', '\'']))
+          .filter(|value| !value.is_empty());
+
+      if let Some(token) = token {
+        return Some(token.to_owned())
+      }
+    }
+  }
+
+  None
+}
+
+/// Finds the password in the output of git credential fill
+pub fn parse_git_credential(output: &str) -> Option<String> {
+  output
+    .lines()
+    .find_map(|line| line.strip_prefix("password="))
+    .filter(|password| !password.is_empty())
+    .map(|password| password.to_owned())
+}
+
+fn get_gh_hosts_file() -> Option<PathBuf> {
+  let config_dir =
+    env::var_os("GH_CONFIG_DIR")
+      .map(PathBuf::from)
+      .or_else(|| env::var_os("XDG_CONFIG_HOME").map(|config_home| PathBuf::from(config_home).join("gh")))
+      .or_else(|| home_dir().map(|home| home.join(".config").join("gh")))?;
+
+  Some(config_dir.join(GH_HOSTS_FILE))
+}
+
+fn git_credential_fill(host: &str) -> Option<String> {
+  let mut child =
+    Command::new("git")
+      .args(["credential", "fill"])
+      // Fail instead of prompting for a username and password
+      .env("GIT_TERMINAL_PROMPT", "0")
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::null())
+      .spawn()
+      .ok()?;
+
+  // stdin is closed when dropped, which ends the request
+  child
+    .stdin
+    .take()?
+    .write_all(format!("protocol=https\nhost={}\n\n", host).as_bytes())
+    .ok()?;
+
+  let output = child.wait_with_output().ok()?;
+
+  if output.status.success() {
+    parse_git_credential(&String::from_utf8_lossy(&output.stdout))
+  } else {
+    None
+  }
+}
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
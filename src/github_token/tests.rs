@@ -0,0 +1,81 @@
+use crate::github_token::{resolve_token, read_token_file, parse_gh_hosts, parse_git_credential};
+use crate::model::*;
+use pretty_assertions::assert_eq;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn uses_the_command_line_token_first() {
+  let (token, source) = resolve_token(Some("cli-token"), "github.com", None).unwrap();
+
+  assert_eq!(token.value(), "cli-token");
+  assert_eq!(source, TokenSource::CommandLine);
+}
+
+#[test]
+fn reads_token_file() {
+  let dir = tempdir().unwrap();
+  let token_file = dir.path().join("token");
+  fs::write(&token_file, "file-token\n").unwrap();
+
+  let token = read_token_file(&token_file).unwrap();
+  assert_eq!(token.value(), "file-token");
+}
+
+#[test]
+fn rejects_empty_or_missing_token_files() {
+  let dir = tempdir().unwrap();
+  let token_file = dir.path().join("token");
+
+  assert!(read_token_file(&token_file).is_err());
+
+  fs::write(&token_file, "  \n").unwrap();
+  assert!(read_token_file(&token_file).is_err());
+}
+
+#[test]
+fn parses_gh_hosts_for_host() {
+  let contents = r#"
+github.com:
+    user: user1
+    oauth_token: gho_github
+    git_protocol: ssh
+github.acme.com:
+    users:
+        user1:
+            oauth_token: "gho_acme"
+    user: user1
+"#;
+
+  assert_eq!(parse_gh_hosts(contents, "github.com"), Some("gho_github".to_owned()));
+  assert_eq!(parse_gh_hosts(contents, "github.acme.com"), Some("gho_acme".to_owned()));
+  assert_eq!(parse_gh_hosts(contents, "github.other.com"), None);
+}
+
+#[test]
+fn parses_gh_hosts_without_token() {
+  // The token is stored in the system keyring
+  let contents = r#"
+github.com:
+    user: user1
+    git_protocol: https
+"#;
+
+  assert_eq!(parse_gh_hosts(contents, "github.com"), None);
+}
+
+#[test]
+fn parses_git_credential_password() {
+  let output = "protocol=https\nhost=github.com\nusername=user1\npassword=ghp_token\n";
+  assert_eq!(parse_git_credential(output), Some("ghp_token".to_owned()));
+
+  assert_eq!(parse_git_credential("protocol=https\nhost=github.com\n"), None);
+}
+
+#[test]
+fn does_not_display_token() {
+  let token = GitHubToken::new("ghp_secret");
+
+  assert!(!token.to_string().contains("ghp_secret"));
+  assert!(!format!("{:?}", token).contains("ghp_secret"));
+}
@@ -1,17 +1,24 @@
 use octocrab::{self, OctocrabBuilder, Octocrab};
 use crate::model::*;
 
+use std::fs;
+use std::path::Path;
 use std::time::Instant;
-use tui_app::render_tui;
+use tui_app::{render_tui, PageLoader};
 use avatar::get_avatars;
-use cli::cli;
-use github::{get_prs3, render_markdown_comments};
-use process::{script_to_run, clone_branch};
-use file_tools::get_extract_path;
+use cli::{cli, parse_repository_argument};
+use github::{get_access_report, get_prs3, get_more_prs, get_inbox_prs, MorePullRequests, get_pr, get_org_repositories, render_markdown_comments};
+use diff_parser::parse_diffs;
+use tools::dedup;
+use process::{script_to_run, clone_branch, clone_commit, update_checkout, get_origin_url, has_uncommitted_changes, switch_to_pull_request, verify_head, get_local_diff, get_git_path, remove_ignored_files, has_tracked_files};
+use file_tools::{get_extract_path, get_sparse_directories, to_file_error, get_generated_file_patterns, add_exclude_patterns, remove_previous_artifacts, GENERATED_FILE_PATTERNS};
 use file_writer::{write_diff_files, write_comment_files};
+use pr_list::print_pull_requests;
 
 mod model;
 mod cli;
+mod config_file;
+mod github_token;
 mod user_dir;
 mod console;
 mod tui_app;
@@ -22,6 +29,8 @@ mod avatar;
 mod log;
 mod process;
 mod file_writer;
+mod diff_parser;
+mod pr_list;
 
 #[tokio::main]
 async fn main() {
@@ -33,6 +42,7 @@ async fn main() {
       match program_result {
         Ok(ProgramStatus::UserQuit) =>  println!("Goodbye!"),
         Ok(ProgramStatus::CompletedSuccessfully) => println!("Purs completed successfully"),
+        Ok(ProgramStatus::ListedPullRequests) => {},
         Err(purs_error) => println!("Purs Error: {}", purs_error)
       }
     },
@@ -45,84 +55,63 @@ async fn main() {
 
 
 async fn handle_program(config: &Config) -> R<ProgramStatus> {
-    //TODO: Move to another function
-    let octocrab =
-        OctocrabBuilder::new()
-        .personal_token(config.token.to_string())
-        .build()
-        .map_err(PursError::from)?;
+    let octocrab = build_octocrab(config)?;
 
-    let pr_start = Instant::now();
-    let pull_requests_raw: Vec<PullRequest> = get_prs3(config, octocrab.clone()).await?;
-
-    // Remove any invalid PRs without a clonable url    let pull_requests =
-    let pull_requests=
-      pull_requests_raw
-      .into_iter()
-      .filter_map(|pr| {
-        match (pr.ssh_url, pr.repo_name) {
-          (Some(ssh_url), Some(repo_name)) => {
-            Some(
-              ValidatedPullRequest {
-                config_owner_repo: pr.config_owner_repo,
-                pr_owner: pr.pr_owner,
-                title : pr.title,
-                pr_number : pr.pr_number,
-                ssh_url: GitRepoSshUrl::new(ssh_url),
-                repo_name: Repo(repo_name),
-                branch_name: RepoBranchName::new(pr.branch_name),
-                head_sha: pr.head_sha,
-                base_sha: pr.base_sha,
-                reviews: pr.reviews,
-                comments: pr.comments,
-                diffs: pr.diffs,
-                draft: pr.draft.unwrap_or(false),
-                created_at: pr.created_at,
-                updated_at: pr.updated_at,
-              }
-            )
-          },
-          _ => None // Filter out PRs that don't have an ssh url or repo name
-        }
-
-      })
-      .collect::<Vec<_>>();
+    if !config.skip_access_check {
+      check_access(config, &octocrab).await?;
+    }
 
-    let time_taken = pr_start.elapsed().as_millis();
+    if let PursCommand::List(format) = &config.command {
+      return handle_list(config, octocrab, format).await
+    }
 
-    println!("GH API calls took {} ms", time_taken);
+    let valid_selection =
+      match &config.direct_pr {
+        Some(direct_pr) => handle_direct_pr(config, octocrab.clone(), direct_pr).await?,
+        None => handle_pr_list(config, octocrab.clone()).await?
+      };
 
-    let valid_selection = handle_user_selection_tui(pull_requests.clone())?;
     match valid_selection {
       ValidSelection::Quit => Ok(ProgramStatus::UserQuit),
       ValidSelection::Pr(mode, pr ) => {
-        let ssh_url = pr.ssh_url.clone();
-        let checkout_path = RepoCheckoutPath::new(get_extract_path(config, &pr)?);
-        let branch_name = pr.branch_name.clone();
-
         println!("mode: {}", mode);
 
-        match mode {
-          Mode::Review => {
-            clone_branch(ssh_url, checkout_path.clone(), branch_name)?;
-            write_diff_files(checkout_path.as_ref(), &pr.diffs)?;
-            handle_comment_generation(octocrab.clone(), config, (*pr).clone(), checkout_path.clone()).await?;
-          },
-          Mode::Edit => {
-            clone_branch(ssh_url, checkout_path.clone(), branch_name)?;
-            handle_comment_generation(octocrab.clone(), config, (*pr).clone(), checkout_path.clone()).await?;
-          },
-        };
+        let checkout_path =
+          match &config.into {
+            Some(local_repository) => {
+              let checkout_path = RepoCheckoutPath::new(local_repository.path.to_string_lossy().to_string());
+              checkout_pr_into(config, &pr, &checkout_path)?;
+              checkout_path
+            },
+            None => {
+              let checkout_path = RepoCheckoutPath::new(get_extract_path(config, &pr)?);
+              checkout_pr(config, &pr, checkout_path.clone())?;
+              checkout_path
+            }
+          };
 
-        match &config.script {
+        prepare_artifacts(&checkout_path, config.artifacts_dir.as_deref())?;
+        let subdirectory = config.artifacts_dir.clone().unwrap_or_default();
+
+        if let Mode::Review = mode {
+          let diffs = get_diffs(config, &pr, &checkout_path)?;
+          let comments = config.include_comments_for(&pr.config_owner_repo).then_some(&pr.comments);
+          write_diff_files(checkout_path.as_ref(), &subdirectory, &diffs, comments)?;
+        }
+
+        handle_comment_generation(octocrab.clone(), config, (*pr).clone(), checkout_path.clone(), &subdirectory).await?;
+
+        let diff_file_list = subdirectory.join(DIFF_FILE_LIST).to_string_lossy().to_string();
+
+        match config.script_for(&pr.config_owner_repo) {
           Some(script) => {
-            script_to_run(script, &mode, &checkout_path)?
+            script_to_run(script, &mode, &checkout_path, &diff_file_list)?
           },
           None => {
             println!();
             println!("Mode: {}", mode);
             println!("Checkout path: {}", checkout_path);
-            println!("Diff file: {}", DIFF_FILE_LIST);
+            println!("Diff file: {}", diff_file_list);
           }
         }
 
@@ -131,19 +120,389 @@ async fn handle_program(config: &Config) -> R<ProgramStatus> {
     }
 }
 
-async fn handle_comment_generation(octocrab: Octocrab, config: &Config, pr: ValidatedPullRequest, checkout_path: RepoCheckoutPath) -> R<()> {
-  if config.include_comments {
-    let avatar_hash = get_avatars(&pr.comments, &config.avatar_cache_dir).await?;
+/// Fails early with a clear error when the token or any repository can't be used
+async fn check_access(config: &Config, octocrab: &Octocrab) -> R<()> {
+    // The inbox searches every repository the token can access
+    let repositories =
+      if config.inbox {
+        vec![]
+      } else {
+        dedup(
+          config
+            .repositories
+            .iter()
+            .cloned()
+            .chain(config.direct_pr.iter().map(|pr| pr.owner_repo.clone()))
+            .collect()
+        )
+      };
+
+    let access_report = get_access_report(octocrab, &repositories).await?;
+
+    // Use stderr so the output of the list command can be piped
+    eprintln!("{}", access_report);
+
+    access_report.validate()
+}
+
+fn build_octocrab(config: &Config) -> R<Octocrab> {
+    let builder =
+      OctocrabBuilder::new()
+        .personal_token(config.token.value().to_owned());
+
+    // GitHub Enterprise Server
+    let builder =
+      match &config.api_url {
+        Some(api_url) => builder.base_uri(api_url.to_string()).map_err(PursError::from)?,
+        None => builder
+      };
+
+    builder
+      .build()
+      .map_err(PursError::from)
+}
+
+async fn handle_pr_list(config: &Config, octocrab: Octocrab) -> R<ValidSelection> {
+    let pr_start = Instant::now();
+    let (pull_requests, more) = get_valid_pull_requests(config, octocrab.clone()).await?;
+
+    let time_taken = pr_start.elapsed().as_millis();
+
+    println!("GH API calls took {} ms", time_taken);
+
+    let page_loader = spawn_page_loader(octocrab, more);
+
+    handle_user_selection_tui(pull_requests, config.profile.as_ref(), page_loader)
+}
+
+/// Loads the next page of PRs in the background each time the TUI requests it
+fn spawn_page_loader(octocrab: Octocrab, more: MorePullRequests) -> PageLoader {
+    let has_more = more.has_more();
+    let (request_sender, mut request_receiver) = tokio::sync::mpsc::unbounded_channel::<()>();
+    let (page_sender, page_receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+      let mut more = more;
+      while request_receiver.recv().await.is_some() {
+        // Keep the previous position on failure, so the next request can retry
+        let page =
+          get_more_prs(more.clone(), octocrab.clone())
+            .await
+            .map(|(pull_requests, next)| {
+              more = next;
+              PageOfPullRequests {
+                pull_requests: validate_pull_requests(pull_requests),
+                has_more: more.has_more()
+              }
+            });
+
+        // The TUI has closed
+        if page_sender.send(page).is_err() {
+          break
+        }
+      }
+    });
+
+    PageLoader::new(request_sender, page_receiver, has_more)
+}
+
+async fn handle_list(config: &Config, octocrab: Octocrab, format: &ListFormat) -> R<ProgramStatus> {
+    let (pull_requests, _) = get_valid_pull_requests(config, octocrab).await?;
+    print_pull_requests(&pull_requests, format)?;
+
+    Ok(ProgramStatus::ListedPullRequests)
+}
+
+async fn get_valid_pull_requests(config: &Config, octocrab: Octocrab) -> R<(Vec<ValidatedPullRequest>, MorePullRequests)> {
+    // The inbox is made up of search results which are not loaded on demand
+    if config.inbox {
+      let pull_requests_raw = get_inbox_prs(&config.filters, config.limit, octocrab).await?;
+      return Ok((validate_pull_requests(pull_requests_raw), MorePullRequests::default()))
+    }
+
+    let repositories = get_repositories(config, octocrab.clone()).await?;
+    let (pull_requests_raw, more) = get_prs3(&repositories, &config.filters, config.limit, octocrab).await?;
+
+    Ok((validate_pull_requests(pull_requests_raw), more))
+}
+
+// Remove any invalid PRs without a clonable url
+fn validate_pull_requests(pull_requests: Vec<PullRequest>) -> Vec<ValidatedPullRequest> {
+    pull_requests
+      .into_iter()
+      .filter_map(validate_pull_request)
+      .collect()
+}
+
+async fn handle_direct_pr(config: &Config, octocrab: Octocrab, direct_pr: &DirectPr) -> R<ValidSelection> {
+    let pr_start = Instant::now();
+    let pull_request = get_pr(octocrab, direct_pr).await?;
+
+    let time_taken = pr_start.elapsed().as_millis();
+
+    println!("GH API calls took {} ms", time_taken);
+
+    validate_pull_request(pull_request)
+      .map(|pr| ValidSelection::Pr(config.mode.clone(), Box::new(pr)))
+      .ok_or_else(|| PursError::InvalidPullRequest(format!("{} does not have a clonable url", direct_pr)))
+}
+
+async fn get_repositories(config: &Config, octocrab: Octocrab) -> R<NonEmptyVec<OwnerRepo>> {
+    let org_repositories =
+      match &config.org {
+        Some(org) => get_org_repositories(octocrab, org, &config.filters).await?,
+        None => vec![]
+      };
+
+    let repositories = dedup(config.repositories.iter().cloned().chain(org_repositories).collect());
+
+    NonEmptyVec::from_vec(repositories)
+      .ok_or_else(|| {
+        let org = config.org.as_ref().map(|o| o.to_string()).unwrap_or_else(|| "-".to_owned());
+        match config.filters.state {
+          PrStateFilter::Open => PursError::NoRepositoriesFound(format!("Could not find any repositories with open PRs for org: {}", org)),
+          _ => PursError::NoRepositoriesFound(format!("Could not find any repositories for org: {}", org))
+        }
+      })
+}
+
+fn validate_pull_request(pr: PullRequest) -> Option<ValidatedPullRequest> {
+    // PRs from a deleted fork can still be cloned from the base repository
+    if pr.ssh_url.is_none() && pr.base_ssh_url.is_none() {
+      return None
+    }
+
+    Some(
+      ValidatedPullRequest {
+        config_owner_repo: pr.config_owner_repo,
+        pr_owner: pr.pr_owner,
+        title : pr.title,
+        pr_number : pr.pr_number,
+        ssh_url: pr.ssh_url.map(GitRepoSshUrl::new),
+        base_ssh_url: pr.base_ssh_url.map(GitRepoSshUrl::new),
+        base_clone_url: pr.base_clone_url.map(GitRepoHttpsUrl::new),
+        repo_name: pr.repo_name.map(Repo),
+        branch_name: RepoBranchName::new(pr.branch_name),
+        head_sha: pr.head_sha,
+        base_sha: pr.base_sha,
+        state: pr.state,
+        merge_commit_sha: pr.merge_commit_sha,
+        reviews: pr.reviews,
+        comments: pr.comments,
+        diffs: pr.diffs,
+        draft: pr.draft.unwrap_or(false),
+        created_at: pr.created_at,
+        updated_at: pr.updated_at,
+      }
+    )
+}
+
+fn checkout_pr(config: &Config, pr: &ValidatedPullRequest, checkout_path: RepoCheckoutPath) -> R<()> {
+    let checkout_dir = Path::new(checkout_path.as_ref());
+
+    if checkout_dir.exists() {
+      if config.force_fresh {
+        println!("Removing existing checkout: {}", checkout_path);
+        fs::remove_dir_all(checkout_dir)
+          .map_err(|e| to_file_error(&format!("Could not remove existing checkout: {}", checkout_path), e))?;
+      } else if checkout_dir.join(".git").exists() {
+        println!("Updating existing checkout: {}", checkout_path);
+        update_pr_checkout(config, pr, checkout_path.clone())?;
+        return verify_head(&checkout_path, &pr.checkout_sha())
+      } else {
+        return Err(PursError::GitError(format!("{} exists but is not a git repository. Use --force-fresh to replace it", checkout_path)))
+      }
+    }
+
+    clone_pr(config, pr, checkout_path.clone())?;
+    verify_head(&checkout_path, &pr.checkout_sha())
+}
+
+fn clone_pr(config: &Config, pr: &ValidatedPullRequest, checkout_path: RepoCheckoutPath) -> R<()> {
+    let clone_protocol = config.clone_protocol_for(&pr.config_owner_repo);
+    // HTTPS clones authenticate with the GitHub token so private repositories can be cloned without SSH keys
+    let credentials = Some(&config.token);
+    let missing_url_error = |url_type: &str| PursError::InvalidPullRequest(format!("PR#{} does not have {}", pr.pr_number, url_type));
+    let pull_ref = format!("refs/pull/{}/head", pr.pr_number);
+    let sparse_directories = get_pr_sparse_directories(config, pr);
+    let settings =
+      |credentials| CheckoutSettings {
+        clone_options: &config.clone_options,
+        sparse_directories: sparse_directories.as_deref(),
+        credentials
+      };
+
+    // Every PR is checked out from the base repository, so the PRs of a repository share a mirror, even when they are from forks
+    let base_mirror_path = config.working_dir.mirror_path(&pr.config_owner_repo.to_string());
+
+    // Checks out a commit, for when the PR branch is not in the base repository
+    let clone_from_base = |fetch_ref: Option<String>, sha: &str| {
+      match clone_protocol {
+        CloneProtocol::Ssh => {
+          let base_ssh_url = pr.base_ssh_url.clone().ok_or_else(|| missing_url_error("a base repository ssh url"))?;
+          clone_commit(base_ssh_url, &base_mirror_path, checkout_path.clone(), fetch_ref, sha, settings(None))
+        },
+        CloneProtocol::Https => {
+          let base_clone_url = pr.base_clone_url.clone().ok_or_else(|| missing_url_error("a base repository https clone url"))?;
+          clone_commit(base_clone_url, &base_mirror_path, checkout_path.clone(), fetch_ref, sha, settings(credentials))
+        }
+      }
+    };
+
+    match pr.state {
+      PullRequestState::Open if pr.is_head_repository_deleted() => {
+        println!("The head repository of PR#{} was deleted. Checking out {} from the base repository", pr.pr_number, pull_ref);
+        clone_from_base(Some(pull_ref), &pr.head_sha)
+      },
+      PullRequestState::Open if pr.is_from_fork() => {
+        println!("PR#{} is from a fork. Checking out {} from the base repository", pr.pr_number, pull_ref);
+        clone_from_base(Some(pull_ref), &pr.head_sha)
+      },
+      PullRequestState::Open => {
+        let branch_name = pr.branch_name.clone();
+        match clone_protocol {
+          CloneProtocol::Ssh => {
+            let base_ssh_url = pr.base_ssh_url.clone().ok_or_else(|| missing_url_error("a base repository ssh url"))?;
+            clone_branch(base_ssh_url, &base_mirror_path, checkout_path.clone(), branch_name, &pr.head_sha, settings(None))
+          },
+          CloneProtocol::Https => {
+            let base_clone_url = pr.base_clone_url.clone().ok_or_else(|| missing_url_error("a base repository https clone url"))?;
+            clone_branch(base_clone_url, &base_mirror_path, checkout_path.clone(), branch_name, &pr.head_sha, settings(credentials))
+          }
+        }
+      },
+      // The PR branch may have been deleted, so checkout the commit from the base repository
+      PullRequestState::Closed | PullRequestState::Merged => {
+        let fetch_ref =
+          match pr.state {
+            PullRequestState::Merged if pr.merge_commit_sha.is_some() => None,
+            _ => Some(pull_ref)
+          };
+
+        clone_from_base(fetch_ref, &pr.checkout_sha())
+      }
+    }
+}
+
+/// The directories to check out when only the directories with changes are checked out
+fn get_pr_sparse_directories(config: &Config, pr: &ValidatedPullRequest) -> Option<Vec<String>> {
+    match (config.clone_options.sparse_changed_only, &pr.diffs) {
+      (true, Some(diffs)) => Some(get_sparse_directories(diffs, config.always_include_for(&pr.config_owner_repo))),
+      (true, None) => {
+        println!("The changes in PR#{} are not known until it is checked out, so every directory will be checked out", pr.pr_number);
+        None
+      },
+      (false, _) => None
+    }
+}
+
+/// The credentials for fetching into a checkout under the working directory. A clone supplied with --into uses its own credentials.
+fn checkout_credentials<'a>(config: &'a Config, pr: &ValidatedPullRequest) -> Option<&'a GitHubToken> {
+    match (&config.into, config.clone_protocol_for(&pr.config_owner_repo)) {
+      (Some(_), _) | (None, CloneProtocol::Ssh) => None,
+      (None, CloneProtocol::Https) => Some(&config.token)
+    }
+}
+
+/// Uses the diff from the GitHub API, unless local diffs were selected or the API couldn't provide the diff
+fn get_diffs(config: &Config, pr: &ValidatedPullRequest, checkout_path: &RepoCheckoutPath) -> R<PullRequestDiff> {
+    let get_local_diffs = || {
+      let credentials = checkout_credentials(config, pr);
+      let local_diff = get_local_diff(checkout_path, &pr.base_sha, &pr.head_sha, &config.diff_options, credentials)?;
+      parse_diffs(&local_diff)
+    };
+
+    match (&config.diff_options.source, &pr.diffs) {
+      (DiffSource::Api, Some(diffs)) => Ok(diffs.clone()),
+      (DiffSource::Api, None) => {
+        println!("The GitHub API could not provide the diff of PR#{}, so it will be generated from the checkout", pr.pr_number);
+        get_local_diffs()
+      },
+      (DiffSource::Local, _) => get_local_diffs()
+    }
+}
+
+/// Checks out a PR into an existing clone of its repository, on a purs/pr-<number> branch
+fn checkout_pr_into(config: &Config, pr: &ValidatedPullRequest, checkout_path: &RepoCheckoutPath) -> R<()> {
+    let origin_url = get_origin_url(checkout_path)?;
+    let origin_matches =
+      match parse_repository_argument(&origin_url, config.github_host()) {
+        Ok(RepositoryArgument::Repository(owner_repo)) => owner_repo.to_string().eq_ignore_ascii_case(&pr.config_owner_repo.to_string()),
+        _ => false
+      };
+
+    if !origin_matches {
+      return Err(PursError::GitError(format!("The origin of {} is {}, which is not {}", checkout_path, origin_url, pr.config_owner_repo)))
+    }
+
+    if has_uncommitted_changes(checkout_path)? {
+      return Err(PursError::GitError(format!("{} has uncommitted changes. Commit or stash them before checking out PR#{}", checkout_path, pr.pr_number)))
+    }
+
+    let branch_name = RepoBranchName::new(format!("purs/pr-{}", pr.pr_number));
+    switch_to_pull_request(checkout_path, pr.pr_number, &branch_name, &pr.head_sha)?;
+    verify_head(checkout_path, &pr.head_sha)
+}
+
+/// Excludes the diff and comment files from git, so they don't show up as changes in the checkout, and removes those of a previous run.
+/// As they are ignored, updating an existing checkout doesn't remove them.
+fn prepare_artifacts(checkout_path: &RepoCheckoutPath, artifacts_dir: Option<&Path>) -> R<()> {
+    // The files of a previous run are removed from the artifacts directory, so it can't be a directory of the repository
+    if let Some(artifacts_dir) = artifacts_dir {
+      if has_tracked_files(checkout_path, artifacts_dir)? {
+        return Err(PursError::GitError(format!("{} has files tracked by git in {}. Use --artifacts-dir to choose another directory", checkout_path, artifacts_dir.to_string_lossy())))
+      }
+    }
+
+    let exclude_file = get_git_path(checkout_path, "info/exclude")?;
+    add_exclude_patterns(&exclude_file, &get_generated_file_patterns(artifacts_dir))?;
+
+    match artifacts_dir {
+      Some(artifacts_dir) => remove_previous_artifacts(Path::new(checkout_path.as_ref()), artifacts_dir),
+      None => remove_ignored_files(checkout_path, &GENERATED_FILE_PATTERNS)
+    }
+}
+
+/// Brings a checkout from a previous run up to date, fetching from the repository it was cloned from
+fn update_pr_checkout(config: &Config, pr: &ValidatedPullRequest, checkout_path: RepoCheckoutPath) -> R<()> {
+    let credentials = checkout_credentials(config, pr);
+    let pull_ref = format!("refs/pull/{}/head", pr.pr_number);
+
+    let fetch_ref =
+      match pr.state {
+        PullRequestState::Open if pr.is_head_repository_deleted() || pr.is_from_fork() => Some(pull_ref),
+        PullRequestState::Open => Some(pr.branch_name.to_string()),
+        PullRequestState::Merged if pr.merge_commit_sha.is_some() => None,
+        PullRequestState::Closed | PullRequestState::Merged => Some(pull_ref)
+      };
+
+    let sparse_directories = get_pr_sparse_directories(config, pr);
+    let settings =
+      CheckoutSettings {
+        clone_options: &config.clone_options,
+        sparse_directories: sparse_directories.as_deref(),
+        credentials
+      };
+
+    update_checkout(checkout_path, fetch_ref, &pr.checkout_sha(), settings)
+}
+
+async fn handle_comment_generation(octocrab: Octocrab, config: &Config, pr: ValidatedPullRequest, checkout_path: RepoCheckoutPath, subdirectory: &Path) -> R<()> {
+  if config.include_comments_for(&pr.config_owner_repo) {
+    // Avatars from GitHub Enterprise Server may require authentication
+    let authorisation = config.api_url.as_ref().map(|api_url| HostAuthorisation::new(api_url, config.token.clone()));
+    let avatar_hash = get_avatars(&pr.comments, &config.avatar_cache_dir, authorisation).await?;
     let rendered_comments =
       render_markdown_comments(&octocrab,  &pr.comments).await?;
 
-    write_comment_files(checkout_path.as_ref(), &rendered_comments, avatar_hash)?;
+    write_comment_files(checkout_path.as_ref(), subdirectory, &rendered_comments, avatar_hash)?;
   }
 
   Ok(())
 }
 
 
-fn handle_user_selection_tui(pulls: Vec<ValidatedPullRequest>) -> R<ValidSelection> {
-  render_tui(pulls)
+fn handle_user_selection_tui(pulls: Vec<ValidatedPullRequest>, profile: Option<&ProfileName>, page_loader: PageLoader) -> R<ValidSelection> {
+  // The TUI blocks, so let the runtime move other tasks (such as loading more PRs) off this thread
+  tokio::task::block_in_place(|| render_tui(pulls, profile, page_loader))
 }
@@ -5,7 +5,7 @@ use std::fmt::{self, Display};
 use std::error::Error;
 use tokio::task::JoinHandle;
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crate::tools::group_by;
 use octocrab::models::Author;
@@ -15,6 +15,11 @@ pub type R<T> = Result<T, PursError>;
 
 pub const DEFAULT_WORKING_DIR: &str = ".purs";
 pub const DIFF_FILE_LIST: &str = "diff_file_list.txt";
+pub const DIFF_MANIFEST: &str = "diff_manifest.json";
+pub const DEFAULT_ARTIFACTS_DIR: &str = ".purs";
+pub const CONFIG_FILE: &str = "config.toml";
+pub const DEFAULT_PR_LIMIT: usize = 20;
+pub const DEFAULT_GITHUB_HOST: &str = "github.com";
 
 #[derive(Debug, Clone)]
 pub struct PullRequest {
@@ -24,12 +29,17 @@ pub struct PullRequest {
     pub pr_number : u64,
     pub ssh_url: Option<String>,
     pub repo_name: Option<String>,
+    pub base_ssh_url: Option<String>,
+    pub base_clone_url: Option<String>,
     pub branch_name: String,
     pub head_sha: String,
     pub base_sha: String,
+    pub state: PullRequestState,
+    pub merge_commit_sha: Option<String>,
     pub reviews: Reviews,
     pub comments: Comments,
-    pub diffs: PullRequestDiff,
+    /// Missing when the GitHub API can't provide the diff, such as for PRs over its size limit
+    pub diffs: Option<PullRequestDiff>,
     pub draft: Option<bool>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
@@ -41,19 +51,70 @@ pub struct ValidatedPullRequest {
     pub pr_owner: Option<User>,
     pub title : String,
     pub pr_number : u64,
-    pub ssh_url: GitRepoSshUrl,
-    pub repo_name: Repo,
+    /// The head repository urls and name are missing when the contributor deleted their fork
+    pub ssh_url: Option<GitRepoSshUrl>,
+    pub repo_name: Option<Repo>,
+    pub base_ssh_url: Option<GitRepoSshUrl>,
+    pub base_clone_url: Option<GitRepoHttpsUrl>,
     pub branch_name: RepoBranchName,
     pub head_sha: String,
     pub base_sha: String,
+    pub state: PullRequestState,
+    pub merge_commit_sha: Option<String>,
     pub reviews: Reviews,
     pub comments: Comments,
-    pub diffs: PullRequestDiff,
+    /// Missing when the GitHub API can't provide the diff, such as for PRs over its size limit
+    pub diffs: Option<PullRequestDiff>,
     pub draft: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+impl ValidatedPullRequest {
+
+  /// The commit to checkout for a PR that is no longer open.
+  /// Merged PRs are checked out at their merge commit and closed PRs at their head commit.
+  pub fn checkout_sha(&self) -> String {
+    match (&self.state, &self.merge_commit_sha) {
+      (PullRequestState::Merged, Some(merge_commit_sha)) => merge_commit_sha.clone(),
+      _ => self.head_sha.clone()
+    }
+  }
+
+  /// The PR branch can't be cloned from the head repository when the contributor deleted their fork
+  pub fn is_head_repository_deleted(&self) -> bool {
+    self.ssh_url.is_none() || self.repo_name.is_none()
+  }
+
+  /// Whether the PR branch is in another repository than the one the PR was made to. The PR branch of a deleted fork is unknown.
+  pub fn is_from_fork(&self) -> bool {
+    self
+      .repo_name
+      .as_ref()
+      .map(|repo_name| !repo_name.0.eq_ignore_ascii_case(&self.config_owner_repo.to_string()))
+      .unwrap_or(true)
+  }
+
+  /// The GitHub API only fails to provide a diff for PRs that are too large
+  pub fn size(&self) -> PrSize {
+    self
+      .changed_files()
+      .map(PrSize::from_changes)
+      .unwrap_or(PrSize::Huge)
+  }
+
+  pub fn changed_files(&self) -> Option<usize> {
+    self.diffs.as_ref().map(|diffs| diffs.0.len())
+  }
+
+  pub fn is_old(&self) -> bool {
+    self
+      .updated_at
+      .map(|t| Utc::now().signed_duration_since(t).num_days() > 7)
+      .unwrap_or(false)
+  }
+}
+
 impl fmt::Display for ValidatedPullRequest {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let repo_name = &self.config_owner_repo.1.0;
@@ -62,6 +123,45 @@ impl fmt::Display for ValidatedPullRequest {
 }
 
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestState {
+  Open,
+  Closed,
+  Merged
+}
+
+impl Display for PullRequestState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      match self {
+        PullRequestState::Open => write!(f, "open"),
+        PullRequestState::Closed => write!(f, "closed"),
+        PullRequestState::Merged => write!(f, "merged"),
+      }
+    }
+}
+
+/// How big a PR is, based on the number of files changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrSize {
+  Small,
+  Medium,
+  Large,
+  Huge
+}
+
+impl PrSize {
+  pub fn from_changes(no_changes: usize) -> Self {
+    match no_changes {
+      0..=10  => PrSize::Small,
+      11..=20 => PrSize::Medium,
+      21..=40 => PrSize::Large,
+      _       => PrSize::Huge
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct PullRequestDiff(pub Vec<GitDiff>);
 
@@ -90,7 +190,7 @@ pub enum ExitCode {
     Terminated
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Owner(pub String);
 
 impl Display for Owner {
@@ -99,7 +199,7 @@ impl Display for Owner {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Repo(pub String);
 
 impl Display for Repo {
@@ -108,7 +208,7 @@ impl Display for Repo {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct OwnerRepo(pub Owner, pub Repo);
 
 impl Display for OwnerRepo {
@@ -171,16 +271,451 @@ impl <T: Clone> NonEmptyVec<T> {
 pub struct Config {
     pub working_dir: WorkingDirectory,
     pub avatar_cache_dir: AvatarCacheDirectory,
-    pub repositories: NonEmptyVec<OwnerRepo>,
+    pub repositories: Vec<OwnerRepo>,
+    pub org: Option<OrgFilter>,
+    pub profile: Option<ProfileName>,
+    pub filters: PrFilters,
+    pub limit: PrLimit,
+    pub inbox: bool,
+    pub api_url: Option<ApiUrl>,
     pub token: GitHubToken,
     pub script: Option<ScriptToRun>,
-    pub include_comments: bool
+    pub include_comments: bool,
+    pub skip_access_check: bool,
+    pub clone_protocol: CloneProtocol,
+    pub clone_options: CloneOptions,
+    pub diff_options: DiffOptions,
+    pub always_include: Vec<String>,
+    pub force_fresh: bool,
+    pub into: Option<LocalRepository>,
+    /// The directory the diff and comment files are written to, relative to the checkout. None writes them next to the changed files
+    pub artifacts_dir: Option<PathBuf>,
+    pub repository_overrides: HashMap<OwnerRepo, RepositoryOverride>,
+    pub direct_pr: Option<DirectPr>,
+    pub mode: Mode,
+    pub command: PursCommand
+}
+
+impl Config {
+
+  /// The host repository and PR URLs are on: github.com or the host of the GitHub Enterprise Server API
+  pub fn github_host(&self) -> &str {
+    self.api_url.as_ref().map(|u| u.host()).unwrap_or(DEFAULT_GITHUB_HOST)
+  }
+
+  pub fn script_for(&self, owner_repo: &OwnerRepo) -> Option<&ScriptToRun> {
+    self
+      .repository_overrides
+      .get(owner_repo)
+      .and_then(|o| o.script.as_ref())
+      .or(self.script.as_ref())
+  }
+
+  pub fn include_comments_for(&self, owner_repo: &OwnerRepo) -> bool {
+    self
+      .repository_overrides
+      .get(owner_repo)
+      .and_then(|o| o.include_comments)
+      .unwrap_or(self.include_comments)
+  }
+
+  pub fn clone_protocol_for(&self, owner_repo: &OwnerRepo) -> CloneProtocol {
+    self
+      .repository_overrides
+      .get(owner_repo)
+      .and_then(|o| o.clone_protocol.clone())
+      .unwrap_or_else(|| self.clone_protocol.clone())
+  }
+
+  pub fn always_include_for(&self, owner_repo: &OwnerRepo) -> &[String] {
+    self
+      .repository_overrides
+      .get(owner_repo)
+      .and_then(|o| o.always_include.as_deref())
+      .unwrap_or(&self.always_include)
+  }
+}
+
+/// Includes the repositories of an owner, optionally filtered by topic and name
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrgFilter {
+  pub owner: Owner,
+  pub topic: Option<String>,
+  pub name_glob: Option<String>
+}
+
+impl Display for OrgFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "{}", self.owner)?;
+      if let Some(topic) = &self.topic {
+        write!(f, " (topic: {})", topic)?;
+      }
+      if let Some(name_glob) = &self.name_glob {
+        write!(f, " (name: {})", name_glob)?;
+      }
+      Ok(())
+    }
+}
+
+/// Filters applied to the listed PRs before any of their details are fetched, either by GitHub or locally
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrFilters {
+  pub author: Option<UserFilter>,
+  pub labels: Vec<String>,
+  pub base: Option<String>,
+  pub exclude_drafts: bool,
+  pub review_requested: Option<UserFilter>,
+  pub state: PrStateFilter,
+  pub since: Option<chrono::Duration>
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum PrStateFilter {
+  #[default]
+  Open,
+  Closed,
+  Merged,
+  All
+}
+
+impl PrStateFilter {
+  pub fn from_short_string(state: &str) -> Option<PrStateFilter> {
+    match state {
+      "open" => Some(PrStateFilter::Open),
+      "closed" => Some(PrStateFilter::Closed),
+      "merged" => Some(PrStateFilter::Merged),
+      "all" => Some(PrStateFilter::All),
+      _ => None
+    }
+  }
+
+  pub fn matches(&self, state: &PullRequestState) -> bool {
+    match self {
+      PrStateFilter::Open => state == &PullRequestState::Open,
+      // Merged PRs are also closed
+      PrStateFilter::Closed => state != &PullRequestState::Open,
+      PrStateFilter::Merged => state == &PullRequestState::Merged,
+      PrStateFilter::All => true,
+    }
+  }
+}
+
+impl PrFilters {
+  /// The pulls API can't filter by author, label, review request or draft status, so these filters use the search API
+  pub fn needs_search(&self) -> bool {
+    self.author.is_some() || !self.labels.is_empty() || self.review_requested.is_some() || self.exclude_drafts
+  }
+}
+
+/// The maximum number of PRs to fetch per repository at a time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrLimit(usize);
+
+impl PrLimit {
+  pub fn new(limit: usize) -> Option<Self> {
+    (limit > 0).then_some(PrLimit(limit))
+  }
+
+  pub fn value(&self) -> usize {
+    self.0
+  }
+}
+
+impl Default for PrLimit {
+  fn default() -> Self {
+    PrLimit(DEFAULT_PR_LIMIT)
+  }
+}
+
+impl Display for PrLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "{}", self.0)
+    }
+}
+
+/// PRs that were loaded on demand, after the initial PRs were displayed
+pub struct PageOfPullRequests {
+  pub pull_requests: Vec<ValidatedPullRequest>,
+  pub has_more: bool
+}
+
+/// A GitHub user, where @me is the owner of the GitHub token
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserFilter {
+  Me,
+  User(String)
+}
+
+impl UserFilter {
+  pub fn new(user: &str) -> Self {
+    match user {
+      "@me" => UserFilter::Me,
+      other => UserFilter::User(other.to_owned())
+    }
+  }
+}
+
+impl Display for UserFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      match self {
+        UserFilter::Me => write!(f, "@me"),
+        UserFilter::User(user) => write!(f, "{}", user),
+      }
+    }
+}
+
+/// The name of a group of repositories defined in the config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileName(String);
+
+impl ProfileName {
+  pub fn new(profile: &str) -> Self {
+    ProfileName(profile.to_owned())
+  }
+}
+
+impl Display for ProfileName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "{}", self.0)
+    }
+}
+
+/// Settings that apply to a single repository, overriding the settings in the config file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepositoryOverride {
+  pub script: Option<ScriptToRun>,
+  pub include_comments: Option<bool>,
+  pub clone_protocol: Option<CloneProtocol>,
+  pub always_include: Option<Vec<String>>
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloneProtocol {
+  #[default]
+  Ssh,
+  Https
+}
+
+impl CloneProtocol {
+  pub fn from_short_string(protocol: &str) -> Option<CloneProtocol> {
+    match protocol {
+      "ssh" => Some(CloneProtocol::Ssh),
+      "https" => Some(CloneProtocol::Https),
+      _ => None
+    }
+  }
+}
+
+impl Display for CloneProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      match self {
+        CloneProtocol::Ssh => write!(f, "ssh"),
+        CloneProtocol::Https => write!(f, "https"),
+      }
+    }
+}
+
+/// Limits how much of a repository is fetched and checked out, for large repositories
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CloneOptions {
+  pub depth: Option<CloneDepth>,
+  pub filter: Option<CloneFilter>,
+  /// Only checkout the directories with changes in the PR and the always_include paths
+  pub sparse_changed_only: bool
+}
+
+/// An existing clone of a repository to checkout PRs into, instead of a new checkout under the working directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalRepository {
+  pub path: PathBuf
+}
+
+/// How a PR is fetched into the mirror of its repository and checked out
+#[derive(Debug, Clone, Copy)]
+pub struct CheckoutSettings<'a> {
+  pub clone_options: &'a CloneOptions,
+  pub sparse_directories: Option<&'a [String]>,
+  pub credentials: Option<&'a GitHubToken>
+}
+
+/// The number of commits to fetch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CloneDepth(usize);
+
+impl CloneDepth {
+  pub fn new(depth: usize) -> Option<Self> {
+    (depth > 0).then_some(CloneDepth(depth))
+  }
+}
+
+impl Display for CloneDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "{}", self.0)
+    }
+}
+
+/// A partial clone filter. Objects that are filtered out are fetched on demand
+#[derive(Debug, Clone, PartialEq)]
+pub enum CloneFilter {
+  BlobNone
+}
+
+impl CloneFilter {
+  pub fn from_short_string(filter: &str) -> Option<CloneFilter> {
+    match filter {
+      "blob:none" => Some(CloneFilter::BlobNone),
+      _ => None
+    }
+  }
+}
+
+impl Display for CloneFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      match self {
+        CloneFilter::BlobNone => write!(f, "blob:none"),
+      }
+    }
+}
+
+/// Where the diff files of a PR are generated from
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum DiffSource {
+  /// The diff from the GitHub API, falling back to a local diff if the API can't provide it
+  #[default]
+  Api,
+  /// A diff generated with git in the checkout
+  Local
+}
+
+impl DiffSource {
+  pub fn from_short_string(source: &str) -> Option<DiffSource> {
+    match source {
+      "api" => Some(DiffSource::Api),
+      "local" => Some(DiffSource::Local),
+      _ => None
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DiffOptions {
+  pub source: DiffSource,
+  /// The number of lines of context in a local diff. Defaults to the git default
+  pub context_lines: Option<usize>,
+  /// Ignore changes in whitespace in a local diff
+  pub ignore_whitespace: bool
+}
+
+/// The contents of the config.toml file in the working directory
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFileToml {
+  pub repos: Option<Vec<String>>,
+  pub script: Option<String>,
+  pub comments: Option<bool>,
+  pub skip_access_check: Option<bool>,
+  pub clone_protocol: Option<CloneProtocol>,
+  pub api_url: Option<String>,
+  pub token_file: Option<String>,
+  pub always_include: Option<Vec<String>>,
+  pub artifacts_dir: Option<String>,
+  #[serde(default)]
+  pub repositories: HashMap<String, RepositoryOverrideToml>,
+  #[serde(default)]
+  pub profiles: HashMap<String, Vec<String>>
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepositoryOverrideToml {
+  pub script: Option<String>,
+  pub comments: Option<bool>,
+  pub clone_protocol: Option<CloneProtocol>,
+  pub always_include: Option<Vec<String>>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PursCommand {
+  Checkout,
+  List(ListFormat)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListFormat {
+  Json,
+  Ndjson,
+  Table
+}
+
+impl ListFormat {
+  pub fn from_short_string(format: &str) -> Option<ListFormat> {
+    match format {
+      "json" => Some(ListFormat::Json),
+      "ndjson" => Some(ListFormat::Ndjson),
+      "table" => Some(ListFormat::Table),
+      _ => None
+    }
+  }
+}
+
+/// A single pull request supplied on the command line, which bypasses the TUI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectPr {
+    pub owner_repo: OwnerRepo,
+    pub pr_number: u64
+}
+
+/// A repository supplied on the command line, which may point to a single PR
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepositoryArgument {
+    Repository(OwnerRepo),
+    PullRequest(DirectPr)
+}
+
+impl Display for DirectPr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "{}#{}", self.owner_repo, self.pr_number)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct GitDiff {
+    /// The path of the file after the change, or before it if the file was deleted
     pub file_name: String,
-    pub contents: String
+    pub contents: String,
+    pub kind: DiffChangeKind,
+    /// The path before the change. None if the file was added
+    pub old_path: Option<String>,
+    /// The path after the change. None if the file was deleted
+    pub new_path: Option<String>,
+    pub added_lines: usize,
+    pub removed_lines: usize,
+    pub hunks: Vec<HunkRange>
+}
+
+/// The lines a hunk of a diff covers before and after the change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HunkRange {
+  pub old_start: usize,
+  pub old_lines: usize,
+  pub new_start: usize,
+  pub new_lines: usize
+}
+
+/// The kind of change made to a file in a diff
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffChangeKind {
+  Added,
+  Modified,
+  Deleted,
+  Renamed,
+  Copied,
+  /// A change to a binary file, which has no lines to diff
+  Binary,
+  /// A change to the mode of a file, without any change to its contents
+  ModeChange
 }
 
 
@@ -189,7 +724,7 @@ pub struct AsyncPullRequestParts {
     pub pull: octocrab::models::pulls::PullRequest,
     pub reviews_handle: JoinHandle<R<Reviews>>,
     pub comments_handle: JoinHandle<R<Comments>>,
-    pub diffs_handle: JoinHandle<R<PullRequestDiff>>
+    pub diffs_handle: JoinHandle<R<Option<PullRequestDiff>>>
 }
 
 #[derive(Debug)]
@@ -255,6 +790,12 @@ pub enum PursError {
     FileError(String, NestedError),
     AvatarCreationError(AvatarCreationErrorType),
     UrlParseError(NestedError),
+    InvalidPullRequest(String),
+    SerializationError(NestedError),
+    NoRepositoriesFound(String),
+    AccessError(String),
+    /// A generated file would be written outside the checkout or into a .git directory
+    UnsafePath(String),
 }
 
 #[derive(Debug, PartialEq)]
@@ -289,6 +830,11 @@ impl Display for PursError {
             PursError::FileError(prefix, error) => write!(f, "PursError.FileError: {}: {}", prefix, error),
             PursError::AvatarCreationError(error) => write!(f, "PursError.AvatarCreationError: {}", error),
             PursError::UrlParseError(error) => write!(f, "PursError.UrlParseError: {}", error),
+            PursError::InvalidPullRequest(error) => write!(f, "PursError.InvalidPullRequest: {}", error),
+            PursError::SerializationError(error) => write!(f, "PursError.SerializationError: {}", error),
+            PursError::NoRepositoriesFound(error) => write!(f, "PursError.NoRepositoriesFound: {}", error),
+            PursError::AccessError(error) => write!(f, "PursError.AccessError: {}", error),
+            PursError::UnsafePath(error) => write!(f, "PursError.UnsafePath: {}", error),
         }
     }
 }
@@ -321,6 +867,12 @@ impl From<tokio::task::JoinError> for PursError {
   }
 }
 
+impl From<serde_json::Error> for PursError {
+  fn from(error: serde_json::Error) -> Self {
+      PursError::SerializationError(NestedError::from(error))
+  }
+}
+
 impl From<reqwest::Error> for PursError {
   fn from(error: reqwest::Error) -> Self {
       PursError::ReqwestError(NestedError::from(error))
@@ -329,7 +881,8 @@ impl From<reqwest::Error> for PursError {
 
 pub enum ProgramStatus {
   UserQuit,
-  CompletedSuccessfully
+  CompletedSuccessfully,
+  ListedPullRequests
 }
 
 pub enum ValidSelection {
@@ -358,6 +911,27 @@ impl Display for GitRepoSshUrl {
     }
 }
 
+#[derive(Debug,Clone)]
+pub struct GitRepoHttpsUrl(String);
+
+impl AsRef<OsStr> for GitRepoHttpsUrl {
+  fn as_ref(&self) -> &OsStr {
+    OsStr::new(&self.0)
+  }
+}
+
+impl GitRepoHttpsUrl {
+  pub fn new(repo: String) -> Self {
+    GitRepoHttpsUrl(repo)
+  }
+}
+
+impl Display for GitRepoHttpsUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "{}", self.0)
+    }
+}
+
 
 #[derive(Clone)]
 pub struct RepoCheckoutPath(String);
@@ -380,6 +954,28 @@ impl AsRef<str> for RepoCheckoutPath {
   }
 }
 
+/// A bare repository that is shared by the checkouts of every PR from a repository
+#[derive(Debug, Clone)]
+pub struct RepoMirrorPath(PathBuf);
+
+impl RepoMirrorPath {
+  pub fn new(path: PathBuf) -> Self {
+    RepoMirrorPath(path)
+  }
+}
+
+impl Display for RepoMirrorPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "{}", self.0.to_string_lossy())
+    }
+}
+
+impl AsRef<Path> for RepoMirrorPath {
+  fn as_ref(&self) -> &Path {
+    self.0.as_path()
+  }
+}
+
 #[derive(Debug,Clone)]
 pub struct RepoBranchName(String);
 
@@ -403,14 +999,7 @@ impl RepoBranchName {
 }
 
 
-#[derive(Debug)]
-pub enum ScriptType {
-  NoScript,
-  Script(ScriptToRun),
-  InvalidScript(String, NestedError)
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ScriptToRun(PathBuf);
 
 impl ScriptToRun {
@@ -477,12 +1066,24 @@ impl WorkingDirectory {
     self.0.as_path()
   }
 
+  pub fn config_file(&self) -> PathBuf {
+    self.0.join(CONFIG_FILE)
+  }
+
   pub fn avatar_cache_dir(&self) -> AvatarCacheDirectory {
     let mut cache_dir = self.0.clone();
     cache_dir.push(".assets");
     cache_dir.push(".avatars");
     AvatarCacheDirectory::new(cache_dir)
   }
+
+  /// The repository is in owner/repo format
+  pub fn mirror_path(&self, repository: &str) -> RepoMirrorPath {
+    let mut mirror_path = self.0.clone();
+    mirror_path.push(".mirrors");
+    mirror_path.push(format!("{}.git", repository));
+    RepoMirrorPath::new(mirror_path)
+  }
 }
 
 impl Display for WorkingDirectory {
@@ -512,7 +1113,7 @@ impl Display for HomeDirectory {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct GitHubToken(String);
 
 impl GitHubToken {
@@ -520,14 +1121,194 @@ impl GitHubToken {
   pub fn new(token: &str) -> Self {
     GitHubToken(token.to_string())
   }
+
+  pub fn value(&self) -> &str {
+    &self.0
+  }
 }
 
+// Never display the token, so it doesn't end up in logs or error messages
 impl Display for GitHubToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "<redacted>")
+    }
+}
+
+impl fmt::Debug for GitHubToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "GitHubToken(<redacted>)")
+    }
+}
+
+/// Where the GitHub token was found
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenSource {
+  CommandLine,
+  GhCli(PathBuf),
+  GitCredentialHelper(String),
+  TokenFile(PathBuf)
+}
+
+impl Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      match self {
+        TokenSource::CommandLine => write!(f, "--token or the GH_ACCESS_TOKEN environment variable"),
+        TokenSource::GhCli(hosts_file) => write!(f, "gh CLI config: {}", hosts_file.to_string_lossy()),
+        TokenSource::GitCredentialHelper(host) => write!(f, "git credential helper for https://{}", host),
+        TokenSource::TokenFile(token_file) => write!(f, "token_file: {}", token_file.to_string_lossy()),
+      }
+    }
+}
+
+/// What the GitHub token can access, checked before any PRs are fetched
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessReport {
+  pub login: String,
+  /// Fine-grained tokens and GitHub Apps don't report their scopes
+  pub scopes: Option<Vec<String>>,
+  /// GitHub Enterprise Server doesn't report a rate limit when rate limiting is disabled
+  pub rate_limit: Option<RateLimitStatus>,
+  pub repositories: Vec<(OwnerRepo, RepositoryAccess)>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitStatus {
+  pub remaining: usize,
+  pub limit: usize,
+  pub reset: Option<DateTime<Utc>>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepositoryAccess {
+  Accessible,
+  NotFound,
+  SsoAuthorisationRequired,
+  Denied(String)
+}
+
+impl Display for RepositoryAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      match self {
+        RepositoryAccess::Accessible => write!(f, "ok"),
+        RepositoryAccess::NotFound => write!(f, "not found, or the token can't access it"),
+        RepositoryAccess::SsoAuthorisationRequired => write!(f, "the token needs to be authorised for SSO with this organisation"),
+        RepositoryAccess::Denied(error) => write!(f, "access denied: {}", error),
+      }
+    }
+}
+
+impl AccessReport {
+  pub const REQUIRED_SCOPE: &'static str = "repo";
+
+  pub fn validate(&self) -> R<()> {
+    let mut errors = vec![];
+
+    if let Some(scopes) = &self.scopes {
+      if !scopes.iter().any(|s| s == Self::REQUIRED_SCOPE) {
+        errors.push(format!("The GitHub token does not have the {} scope. Scopes: [{}]", Self::REQUIRED_SCOPE, scopes.join(", ")))
+      }
+    }
+
+    if let Some(rate_limit) = self.rate_limit.as_ref().filter(|r| r.remaining == 0) {
+      let reset = rate_limit.reset.map(|r| r.to_rfc3339()).unwrap_or_else(|| "-".to_owned());
+      errors.push(format!("The GitHub API rate limit has been exceeded. It resets at: {}", reset))
+    }
+
+    self
+      .repositories
+      .iter()
+      .filter(|(_, access)| access != &RepositoryAccess::Accessible)
+      .for_each(|(owner_repo, access)| errors.push(format!("Can't access {}: {}", owner_repo, access)));
+
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(PursError::AccessError(errors.join("\n\t")))
+    }
+  }
+}
+
+impl Display for AccessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      let scopes =
+        self
+          .scopes
+          .as_ref()
+          .map(|s| s.join(", "))
+          .unwrap_or_else(|| "not reported".to_owned());
+
+      writeln!(f, "Authenticated as: {} (scopes: {})", self.login, scopes)?;
+      match &self.rate_limit {
+        Some(rate_limit) => write!(f, "Rate limit: {}/{} remaining", rate_limit.remaining, rate_limit.limit)?,
+        None => write!(f, "Rate limit: not reported")?
+      }
+
+      if !self.repositories.is_empty() {
+        write!(f, "\nRepository access:")?;
+        for (owner_repo, access) in &self.repositories {
+          write!(f, "\n  {}: {}", owner_repo, access)?;
+        }
+      }
+
+      Ok(())
+    }
+}
+
+/// The base url of the GitHub API. Eg. https://github.acme.com/api/v3 for GitHub Enterprise Server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiUrl(url::Url);
+
+impl ApiUrl {
+  pub fn new(api_url: &str) -> Option<Self> {
+    url::Url::parse(api_url)
+      .ok()
+      .filter(|url| (url.scheme() == "https" || url.scheme() == "http") && url.host_str().is_some())
+      .map(ApiUrl)
+  }
+
+  pub fn host(&self) -> &str {
+    self.0.host_str().unwrap_or_default()
+  }
+
+  pub fn scheme(&self) -> &str {
+    self.0.scheme()
+  }
+}
+
+impl Display for ApiUrl {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
       write!(f, "{}", self.0)
     }
 }
 
+/// Authorises downloads from a GitHub Enterprise Server host, which may only serve avatars to authenticated users
+#[derive(Debug, Clone)]
+pub struct HostAuthorisation {
+  scheme: String,
+  host: String,
+  token: GitHubToken
+}
+
+impl HostAuthorisation {
+  pub fn new(api_url: &ApiUrl, token: GitHubToken) -> Self {
+    HostAuthorisation {
+      scheme: api_url.scheme().to_owned(),
+      host: api_url.host().to_owned(),
+      token
+    }
+  }
+
+  /// The token is only supplied to the enterprise host or its subdomains (such as avatars.<host>), over https or the scheme of the API
+  pub fn token_for(&self, url: &Url) -> Option<&GitHubToken> {
+    let url = url::Url::parse(&url.0).ok()?;
+    let url_host = url.host_str()?;
+    let scheme_matches = url.scheme() == "https" || url.scheme() == self.scheme;
+    let host_matches = url_host == self.host || url_host.ends_with(&format!(".{}", self.host));
+
+    (scheme_matches && host_matches).then_some(&self.token)
+  }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct CommandLineArgumentFailure(String);
 
@@ -565,7 +1346,9 @@ pub enum ReviewState {
 #[derive(Debug, Clone)]
 pub struct Review {
   pub user: Option<String>,
+  #[allow(dead_code)]
   pub comment: Option<String>,
+  pub commit_id: Option<String>,
   pub state: ReviewState
 }
 
@@ -587,6 +1370,32 @@ impl Reviews {
       .filter_map(|r| r.user.clone())
       .collect()
   }
+
+  /// Whether the user reviewed the PR, but not at the current head commit
+  pub fn changed_since_reviewed_by(&self, user: &str, head_sha: &str) -> bool {
+    let user_reviews = self.reviews_by(user).collect::<Vec<_>>();
+
+    !user_reviews.is_empty() && user_reviews.iter().all(|r| r.commit_id.as_deref() != Some(head_sha))
+  }
+
+  pub fn reviewed_by(&self, user: &str) -> bool {
+    self.reviews_by(user).next().is_some()
+  }
+
+  fn reviews_by<'a>(&'a self, user: &'a str) -> impl Iterator<Item = &'a Review> {
+    self
+      .reviews
+      .iter()
+      .filter(|r| r.user.as_deref().map(|u| u.eq_ignore_ascii_case(user)).unwrap_or(false))
+  }
+
+  pub fn approval_count(&self) -> usize {
+    self
+      .reviews
+      .iter()
+      .filter(|r| matches!(r.state, ReviewState::Approved))
+      .count()
+  }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -627,6 +1436,7 @@ impl Display for Markdown {
 #[derive(Debug, Clone)]
 pub struct Comment {
   pub comment_id: CommentId,
+  #[allow(dead_code)]
   pub diff_hunk: String,
   pub body: String,
   pub markdown_body: Option<Markdown>,
@@ -634,6 +1444,7 @@ pub struct Comment {
   pub comment_url: Url,
   pub line: Option<LineNumber>,
   pub file_name: FileName,
+  #[allow(dead_code)]
   pub in_reply_to_id: Option<CommentId>
 }
 
@@ -672,6 +1483,11 @@ impl Comments {
   pub fn is_empty(&self) -> bool {
     self.comments.is_empty()
   }
+
+  /// The number of comments on lines of a file, which are the comments written to its comment file
+  pub fn line_comment_count(&self, file_name: &str) -> usize {
+    self.comments.iter().filter(|c| c.line.is_some() && c.file_name.0 == file_name).count()
+  }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -773,6 +1589,53 @@ impl From<&Url> for String {
 
 
 
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PullRequestSummaryJson {
+  pub number: u64,
+  pub title: String,
+  pub repo: String,
+  pub pr_repo: Option<String>,
+  pub head_repository_deleted: bool,
+  pub branch: String,
+  pub author: Option<String>,
+  pub state: PullRequestState,
+  pub draft: bool,
+  pub reviews: usize,
+  pub approvals: usize,
+  pub comments: usize,
+  pub changed_files: Option<usize>,
+  pub size: PrSize,
+  pub old: bool,
+  pub head_sha: String,
+  pub created_at: Option<String>,
+  pub updated_at: Option<String>,
+}
+
+impl From<&ValidatedPullRequest> for PullRequestSummaryJson {
+  fn from(pr: &ValidatedPullRequest) -> Self {
+    PullRequestSummaryJson {
+      number: pr.pr_number,
+      title: pr.title.clone(),
+      repo: pr.config_owner_repo.to_string(),
+      pr_repo: pr.repo_name.as_ref().map(|r| r.to_string()),
+      head_repository_deleted: pr.is_head_repository_deleted(),
+      branch: pr.branch_name.to_string(),
+      author: pr.pr_owner.clone().map(|u| u.user_name()),
+      state: pr.state.clone(),
+      draft: pr.draft,
+      reviews: pr.reviews.count(),
+      approvals: pr.reviews.approval_count(),
+      comments: pr.comments.count(),
+      changed_files: pr.changed_files(),
+      size: pr.size(),
+      old: pr.is_old(),
+      head_sha: pr.head_sha.clone(),
+      created_at: pr.created_at.map(|t| t.to_rfc3339()),
+      updated_at: pr.updated_at.map(|t| t.to_rfc3339()),
+    }
+  }
+}
+
 #[derive(Serialize, Debug, PartialEq)]
 pub struct CommentJson {
   pub user_name: String,
@@ -797,6 +1660,68 @@ pub struct FileCommentsJson {
   pub file_comments: Vec<LineCommentsJson>
 }
 
+/// Describes the changes to each file of a PR, so scripts don't have to parse the diff files
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DiffManifestJson {
+  pub files: Vec<DiffManifestFileJson>
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DiffManifestFileJson {
+  pub file_name: String,
+  pub kind: DiffChangeKind,
+  pub old_path: Option<String>,
+  pub new_path: Option<String>,
+  pub added_lines: usize,
+  pub removed_lines: usize,
+  pub hunks: Vec<HunkRange>,
+  /// The path of the diff file, relative to the checkout
+  pub diff_file: String,
+  /// The path of the comment file, relative to the checkout. None if the file has no comments or comments are not included
+  pub comment_file: Option<String>,
+  /// The number of review comments on lines of the file, which are the comments in the comment file
+  pub comment_count: usize
+}
+
+/// The files generated for a changed file, as paths relative to the checkout. The paths are checked before any files are written,
+/// so the manifest describes the same files as the diff file list.
+#[derive(Debug, Clone)]
+pub struct GeneratedFilePaths<'a> {
+  pub diff: &'a GitDiff,
+  pub diff_file: String,
+  /// None if the file has no comments or comments are not included
+  pub comment_file: Option<String>
+}
+
+impl DiffManifestJson {
+  pub fn new(generated_files: &[GeneratedFilePaths], comments: Option<&Comments>) -> Self {
+    let files =
+      generated_files
+        .iter()
+        .map(|generated| {
+          let d = generated.diff;
+
+          DiffManifestFileJson {
+            file_name: d.file_name.clone(),
+            kind: d.kind,
+            old_path: d.old_path.clone(),
+            new_path: d.new_path.clone(),
+            added_lines: d.added_lines,
+            removed_lines: d.removed_lines,
+            hunks: d.hunks.clone(),
+            diff_file: generated.diff_file.clone(),
+            comment_file: generated.comment_file.clone(),
+            comment_count: comments.map(|c| c.line_comment_count(&d.file_name)).unwrap_or(0)
+          }
+        })
+        .collect();
+
+    DiffManifestJson {
+      files
+    }
+  }
+}
+
 impl CommentJson {
 
 
@@ -932,6 +1857,7 @@ impl fmt::Display for AvatarInfo {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Mode {
   Review,
   Edit
@@ -955,6 +1881,14 @@ impl Mode {
       Mode::Edit => "edit".to_owned()
     }
   }
+
+  pub fn from_short_string(mode: &str) -> Option<Mode> {
+    match mode {
+      "review" => Some(Mode::Review),
+      "edit" => Some(Mode::Edit),
+      _ => None
+    }
+  }
 }
 
 
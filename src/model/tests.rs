@@ -2,7 +2,8 @@ use std::collections::HashMap;
 use pretty_assertions::assert_eq;
 use crate::model::{Markdown, FileUrl};
 
-use super::{CommentJson, Comment, Comments, FileName, LineNumber, Url, User, UserId, CommentId, FileCommentsJson, LineCommentsJson};
+use super::{Review, Reviews, ReviewState, CommentJson, Comment, Comments, FileName, LineNumber, Url, User, UserId, CommentId, FileCommentsJson, LineCommentsJson};
+use super::{DiffManifestJson, DiffManifestFileJson, DiffChangeKind, GitDiff, HunkRange, PullRequestDiff, GeneratedFilePaths};
 
 #[test]
 fn comment_json_grouped_by_line() {
@@ -195,3 +196,116 @@ fn comment_json_grouped_by_line() {
 }
 
 
+
+#[test]
+fn reviews_changed_since_reviewed_by_user() {
+  let review = |user: &str, commit_id: &str| {
+    Review {
+      user: Some(user.to_owned()),
+      comment: None,
+      commit_id: Some(commit_id.to_owned()),
+      state: ReviewState::Commented
+    }
+  };
+
+  let reviews = Reviews { reviews: vec![review("user1", "sha1"), review("user2", "sha2")] };
+
+  assert!(reviews.changed_since_reviewed_by("user1", "sha2"));
+  assert!(!reviews.changed_since_reviewed_by("User2", "sha2"));
+  // Not reviewed by this user
+  assert!(!reviews.changed_since_reviewed_by("user3", "sha2"));
+
+  assert!(reviews.reviewed_by("User1"));
+  assert!(!reviews.reviewed_by("user3"));
+}
+
+#[test]
+fn diff_manifest_describes_diff_and_comment_files() {
+  let comment = |comment_id: u64, line: Option<u64>, file_name: &str| {
+    Comment {
+      comment_id: CommentId::new(comment_id),
+      diff_hunk: "diff hunk".to_owned(),
+      body: "body".to_owned(),
+      markdown_body: None,
+      author: None,
+      comment_url: Url::new("https://sample.data/comment".to_owned()),
+      line: line.map(LineNumber::new),
+      file_name: FileName::new(file_name.to_owned()),
+      in_reply_to_id: None
+    }
+  };
+
+  // Comments without a line are not written to the comment file
+  let comments = Comments { comments: vec![comment(1, Some(3), "src/new.rs"), comment(2, Some(4), "src/new.rs"), comment(3, None, "src/new.rs")] };
+  let hunk = HunkRange { old_start: 3, old_lines: 1, new_start: 3, new_lines: 2 };
+
+  let diffs =
+    PullRequestDiff(vec![
+      GitDiff {
+        file_name: "src/new.rs".to_owned(),
+        contents: String::new(),
+        kind: DiffChangeKind::Renamed,
+        old_path: Some("src/old.rs".to_owned()),
+        new_path: Some("src/new.rs".to_owned()),
+        added_lines: 2,
+        removed_lines: 1,
+        hunks: vec![hunk.clone()]
+      },
+      GitDiff {
+        file_name: "image.png".to_owned(),
+        contents: String::new(),
+        kind: DiffChangeKind::Binary,
+        old_path: Some("image.png".to_owned()),
+        new_path: Some("image.png".to_owned()),
+        added_lines: 0,
+        removed_lines: 0,
+        hunks: vec![]
+      }
+    ]);
+
+  let expected_files =
+    vec![
+      DiffManifestFileJson {
+        file_name: "src/new.rs".to_owned(),
+        kind: DiffChangeKind::Renamed,
+        old_path: Some("src/old.rs".to_owned()),
+        new_path: Some("src/new.rs".to_owned()),
+        added_lines: 2,
+        removed_lines: 1,
+        hunks: vec![hunk],
+        diff_file: ".purs/src/new.rs.diff".to_owned(),
+        comment_file: Some(".purs/src/new.rs.comment".to_owned()),
+        comment_count: 2
+      },
+      DiffManifestFileJson {
+        file_name: "image.png".to_owned(),
+        kind: DiffChangeKind::Binary,
+        old_path: Some("image.png".to_owned()),
+        new_path: Some("image.png".to_owned()),
+        added_lines: 0,
+        removed_lines: 0,
+        hunks: vec![],
+        diff_file: ".purs/image.png.diff".to_owned(),
+        comment_file: None,
+        comment_count: 0
+      }
+    ];
+
+  let generated_files =
+    vec![
+      GeneratedFilePaths { diff: &diffs.0[0], diff_file: ".purs/src/new.rs.diff".to_owned(), comment_file: Some(".purs/src/new.rs.comment".to_owned()) },
+      GeneratedFilePaths { diff: &diffs.0[1], diff_file: ".purs/image.png.diff".to_owned(), comment_file: None }
+    ];
+
+  assert_eq!(DiffManifestJson::new(&generated_files, Some(&comments)).files, expected_files);
+
+  // Without comments there are no comment files
+  let without_comment_files =
+    vec![
+      GeneratedFilePaths { diff: &diffs.0[0], diff_file: "src/new.rs.diff".to_owned(), comment_file: None }
+    ];
+  let without_comments = DiffManifestJson::new(&without_comment_files, None);
+  assert_eq!(without_comments.files[0].diff_file, "src/new.rs.diff");
+  assert_eq!(without_comments.files[0].comment_file, None);
+  assert_eq!(without_comments.files[0].comment_count, 0);
+}
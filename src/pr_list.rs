@@ -0,0 +1,88 @@
+use crate::model::*;
+
+const TABLE_HEADERS: [&str; 12] = ["PR#", "TITLE", "REPO", "AUTHOR", "STATE", "DRAFT", "REVIEWS", "APPROVALS", "COMMENTS", "CHANGES", "CREATED", "UPDATED"];
+
+pub fn print_pull_requests(pull_requests: &[ValidatedPullRequest], format: &ListFormat) -> R<()> {
+  let summaries =
+    pull_requests
+      .iter()
+      .map(PullRequestSummaryJson::from)
+      .collect::<Vec<_>>();
+
+  let output =
+    match format {
+      ListFormat::Json => serde_json::to_string_pretty(&summaries)?,
+      ListFormat::Ndjson => render_ndjson(&summaries)?,
+      ListFormat::Table => render_table(&summaries),
+    };
+
+  println!("{}", output);
+  Ok(())
+}
+
+pub fn render_ndjson(summaries: &[PullRequestSummaryJson]) -> R<String> {
+  let lines =
+    summaries
+      .iter()
+      .map(serde_json::to_string)
+      .collect::<Result<Vec<_>, _>>()?;
+
+  Ok(lines.join("\n"))
+}
+
+pub fn render_table(summaries: &[PullRequestSummaryJson]) -> String {
+  let header = TABLE_HEADERS.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+
+  let rows =
+    summaries
+      .iter()
+      .map(|s| {
+        vec![
+          s.number.to_string(),
+          s.title.clone(),
+          s.repo.clone(),
+          s.author.clone().unwrap_or_else(|| "-".to_owned()),
+          s.state.to_string(),
+          s.draft.to_string(),
+          s.reviews.to_string(),
+          s.approvals.to_string(),
+          s.comments.to_string(),
+          s.changed_files.map(|c| c.to_string()).unwrap_or_else(|| "-".to_owned()),
+          s.created_at.clone().unwrap_or_else(|| "-".to_owned()),
+          s.updated_at.clone().unwrap_or_else(|| "-".to_owned()),
+        ]
+      });
+
+  let all_rows = std::iter::once(header).chain(rows).collect::<Vec<_>>();
+
+  let widths =
+    (0..TABLE_HEADERS.len())
+      .map(|column| {
+        all_rows
+          .iter()
+          .map(|row| row[column].chars().count())
+          .max()
+          .unwrap_or(0)
+      })
+      .collect::<Vec<_>>();
+
+  all_rows
+    .iter()
+    .map(|row| {
+      row
+        .iter()
+        .zip(widths.iter())
+        .map(|(value, width)| format!("{:<width$}", value, width = width))
+        .collect::<Vec<_>>()
+        .join("  ")
+        .trim_end()
+        .to_owned()
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,54 @@
+use crate::pr_list::{render_table, render_ndjson};
+use crate::model::{PullRequestSummaryJson, PrSize, PullRequestState};
+use pretty_assertions::assert_eq;
+
+fn summary(number: u64, title: &str, author: Option<&str>) -> PullRequestSummaryJson {
+  PullRequestSummaryJson {
+    number,
+    title: title.to_owned(),
+    repo: "ssanj/purs".to_owned(),
+    pr_repo: Some("ssanj/purs".to_owned()),
+    head_repository_deleted: false,
+    branch: "feature".to_owned(),
+    author: author.map(|a| a.to_owned()),
+    state: PullRequestState::Open,
+    draft: false,
+    reviews: 2,
+    approvals: 1,
+    comments: 3,
+    changed_files: Some(4),
+    size: PrSize::Small,
+    old: false,
+    head_sha: "abc".to_owned(),
+    created_at: Some("2022-01-01T00:00:00+00:00".to_owned()),
+    updated_at: None,
+  }
+}
+
+#[test]
+fn renders_aligned_table() {
+  let summaries = vec![summary(1, "Short", Some("user1")), summary(100, "A longer title", None)];
+
+  let table = render_table(&summaries);
+
+  let expected =
+    [
+      "PR#  TITLE           REPO        AUTHOR  STATE  DRAFT  REVIEWS  APPROVALS  COMMENTS  CHANGES  CREATED                    UPDATED",
+      "1    Short           ssanj/purs  user1   open   false  2        1          3         4        2022-01-01T00:00:00+00:00  -",
+      "100  A longer title  ssanj/purs  -       open   false  2        1          3         4        2022-01-01T00:00:00+00:00  -",
+    ].join("\n");
+
+  assert_eq!(table, expected)
+}
+
+#[test]
+fn renders_one_json_object_per_line() {
+  let summaries = vec![summary(1, "Short", Some("user1")), summary(2, "Other", None)];
+
+  let ndjson = render_ndjson(&summaries).unwrap();
+  let lines = ndjson.lines().collect::<Vec<_>>();
+
+  assert_eq!(lines.len(), 2);
+  assert!(lines[0].starts_with(r#"{"number":1,"title":"Short""#));
+  assert!(lines[1].contains(r#""size":"small""#));
+}
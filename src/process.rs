@@ -1,16 +1,19 @@
 use crate::model::*;
 use crate::log::*;
-use std::process::Command;
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 
-pub fn script_to_run(script: &ScriptToRun, mode: &Mode, checkout_path: &RepoCheckoutPath) -> R<()> {
+pub fn script_to_run(script: &ScriptToRun, mode: &Mode, checkout_path: &RepoCheckoutPath, diff_file_list: &str) -> R<()> {
   let mut command = Command::new(script.to_string());
   command
     .arg(checkout_path.to_string()) //arg1 -> checkout dir
     .arg(mode.short_string()); //arg2 -> mode
 
    if let Mode::Review = mode {
-      command.arg(DIFF_FILE_LIST); //arg3 -> diff file list
+      command.arg(diff_file_list); //arg3 -> diff file list
    };
 
    match command.status() {
@@ -30,33 +33,526 @@ pub fn script_to_run(script: &ScriptToRun, mode: &Mode, checkout_path: &RepoChec
   }
 }
 
-pub fn clone_branch(ssh_url: GitRepoSshUrl, checkout_path: RepoCheckoutPath, branch_name: RepoBranchName) -> R<()> {
-    print_info(format!("git clone {} -b {} {}", ssh_url, branch_name, checkout_path));
-    let mut command = Command::new("git") ;
-      command
-      .arg("clone")
-      .arg(ssh_url)
-      .arg("-b")
+// The token is read from the environment by the credential helper, so it never appears in the arguments to git
+const GIT_TOKEN_ENV_VAR: &str = "PURS_GIT_TOKEN";
+const TOKEN_CREDENTIAL_HELPER: &str = "!f() { test \"$1\" = get && echo username=x-access-token && echo \"password=$PURS_GIT_TOKEN\"; }; f";
+
+/// Checks out the head of a PR's branch into a worktree of the repository's mirror. The worktree is on a local branch that tracks the
+/// remote branch, unless the branch is already checked out in the worktree of another commit of the PR.
+/// If the branch has moved on from the PR's head since the PR was fetched, the worktree is detached at the PR's head, so it matches the diffs.
+pub fn clone_branch<U: AsRef<OsStr> + Display>(repo_url: U, mirror_path: &RepoMirrorPath, checkout_path: RepoCheckoutPath, branch_name: RepoBranchName, sha: &str, settings: CheckoutSettings) -> R<()> {
+    let remote_branch = format!("origin/{}", branch_name);
+    let refspec = format!("+refs/heads/{}:refs/remotes/{}", branch_name, remote_branch);
+    update_mirror(&repo_url, mirror_path, Some(refspec), settings)?;
+
+    let remote_branch_sha = get_commit_sha(mirror_path.as_ref(), &remote_branch)?;
+
+    if remote_branch_sha != sha {
+      print_info(format!("{} has moved from {} to {} since the PR was fetched, so {} will be detached at {}", branch_name, sha, remote_branch_sha, checkout_path, sha));
+
+      // The commit may no longer be on the branch if it was force pushed
+      if !has_commit(mirror_path, sha) {
+        update_mirror(&repo_url, mirror_path, Some(sha.to_owned()), settings)
+          .map_err(|e| {
+            let e1 = PursError::GitError(format!("Could not fetch {}, which is no longer on {}. Run purs again to get the latest changes to the PR", sha, branch_name));
+            PursError::MultipleErrors(vec![e1, e])
+          })?;
+      }
+
+      add_worktree(mirror_path, &checkout_path, &["--detach"], sha, settings)
+    } else if is_branch_in_worktree(mirror_path, &branch_name)? {
+      print_info(format!("{} is checked out in another worktree, so {} will be detached", branch_name, checkout_path));
+      add_worktree(mirror_path, &checkout_path, &["--detach"], sha, settings)
+    } else {
+      add_worktree(mirror_path, &checkout_path, &["--track", "-B", branch_name.as_ref()], &remote_branch, settings)
+    }
+}
+
+/// Checks out a specific commit into a worktree of the repository's mirror, optionally fetching a ref that contains it first.
+/// This is used for PRs that are no longer open, where the PR branch may have been deleted.
+pub fn clone_commit<U: AsRef<OsStr> + Display>(repo_url: U, mirror_path: &RepoMirrorPath, checkout_path: RepoCheckoutPath, fetch_ref: Option<String>, sha: &str, settings: CheckoutSettings) -> R<()> {
+    // Keep the fetched ref in the mirror, so its commits are not garbage collected
+    let refspec =
+      fetch_ref.map(|git_ref| {
+        if git_ref.starts_with("refs/") {
+          format!("+{}:{}", git_ref, git_ref)
+        } else {
+          git_ref
+        }
+      });
+
+    if has_commit(mirror_path, sha) && refspec.is_none() {
+      update_mirror_remote(repo_url, mirror_path)?;
+    } else {
+      update_mirror(repo_url, mirror_path, refspec, settings)?;
+    }
+
+    add_worktree(mirror_path, &checkout_path, &["--detach"], sha, settings)
+}
+
+/// Creates the bare mirror of a repository if it doesn't exist and fetches the refspec into it.
+/// Without a refspec every branch is fetched.
+fn update_mirror<U: AsRef<OsStr> + Display>(repo_url: U, mirror_path: &RepoMirrorPath, refspec: Option<String>, settings: CheckoutSettings) -> R<()> {
+    update_mirror_remote(repo_url, mirror_path)?;
+    let clone_options = settings.clone_options;
+
+    if let Some(filter) = &clone_options.filter {
+      // Allows the filtered out objects to be fetched from origin when they are needed
+      set_mirror_config(mirror_path, "remote.origin.promisor", "true")?;
+      set_mirror_config(mirror_path, "remote.origin.partialclonefilter", &filter.to_string())?;
+    }
+
+    let mut fetch_args = get_fetch_options(mirror_path.as_ref(), clone_options)?;
+    fetch_args.push("origin".to_owned());
+    fetch_args.extend(refspec);
+
+    print_info(format!("git fetch {}", fetch_args.join(" ")));
+    let mut fetch_command = git_command(settings.credentials);
+      fetch_command
+      .arg("-C")
+      .arg(mirror_path.as_ref())
+      .arg("fetch")
+      .args(fetch_args);
+
+    run_git(&mut fetch_command)
+}
+
+/// The depth and filter to fetch with. The mirror is shared by every checkout of a repository, so once a fetch without a depth is
+/// made, the history a shallow fetch left out is fetched too.
+fn get_fetch_options(repository_path: &Path, clone_options: &CloneOptions) -> R<Vec<String>> {
+    let mut fetch_options = vec![];
+
+    match clone_options.depth {
+      Some(depth) => fetch_options.push(format!("--depth={}", depth)),
+      None if is_shallow(repository_path)? => fetch_options.push("--unshallow".to_owned()),
+      None => ()
+    }
+
+    if let Some(filter) = &clone_options.filter {
+      fetch_options.push(format!("--filter={}", filter));
+    }
+
+    Ok(fetch_options)
+}
+
+fn is_shallow(repository_path: &Path) -> R<bool> {
+    let mut rev_parse_command = Command::new("git");
+      rev_parse_command
+      .arg("-C")
+      .arg(repository_path)
+      .arg("rev-parse")
+      .arg("--is-shallow-repository");
+
+    get_git_output(&mut rev_parse_command).map(|shallow| shallow == "true")
+}
+
+/// The remote url is updated each time, as the clone protocol may have changed since the mirror was created
+fn update_mirror_remote<U: AsRef<OsStr> + Display>(repo_url: U, mirror_path: &RepoMirrorPath) -> R<()> {
+    let remote_action =
+      if mirror_path.as_ref().exists() {
+        "set-url"
+      } else {
+        print_info(format!("git init --bare {}", mirror_path));
+        let mut init_command = Command::new("git");
+          init_command
+          .arg("init")
+          .arg("--quiet")
+          .arg("--bare")
+          .arg(mirror_path.as_ref());
+
+        run_git(&mut init_command)?;
+        "add"
+      };
+
+    let mut remote_command = Command::new("git");
+      remote_command
+      .arg("-C")
+      .arg(mirror_path.as_ref())
+      .arg("remote")
+      .arg(remote_action)
+      .arg("origin")
+      .arg(repo_url);
+
+    run_git(&mut remote_command)
+}
+
+fn set_mirror_config(mirror_path: &RepoMirrorPath, key: &str, value: &str) -> R<()> {
+    let mut config_command = Command::new("git");
+      config_command
+      .arg("-C")
+      .arg(mirror_path.as_ref())
+      .arg("config")
+      .arg(key)
+      .arg(value);
+
+    run_git(&mut config_command)
+}
+
+/// Adds a worktree at the checkout path. With sparse directories only those directories and the files in the root of the repository
+/// are checked out.
+fn add_worktree(mirror_path: &RepoMirrorPath, checkout_path: &RepoCheckoutPath, options: &[&str], commit: &str, settings: CheckoutSettings) -> R<()> {
+    // Forget worktrees whose directories were deleted, so their paths can be reused
+    let mut prune_command = Command::new("git");
+      prune_command
+      .arg("-C")
+      .arg(mirror_path.as_ref())
+      .arg("worktree")
+      .arg("prune");
+
+    run_git(&mut prune_command)?;
+
+    let no_checkout_option = settings.sparse_directories.map(|_| "--no-checkout");
+
+    print_info(format!("git worktree add {} {} {}", options.join(" "), checkout_path, commit));
+    // Files filtered out of a partial clone are fetched while checking out
+    let mut worktree_command = git_command(settings.credentials);
+      worktree_command
+      .arg("-C")
+      .arg(mirror_path.as_ref())
+      .arg("worktree")
+      .arg("add")
+      .arg("--quiet")
+      .args(no_checkout_option)
+      .args(options)
+      .arg(checkout_path.as_ref())
+      .arg(commit);
+
+    run_git(&mut worktree_command)?;
+
+    match settings.sparse_directories {
+      Some(directories) => sparse_checkout(checkout_path, directories, settings.credentials),
+      None => Ok(())
+    }
+}
+
+fn sparse_checkout(checkout_path: &RepoCheckoutPath, directories: &[String], credentials: Option<&GitHubToken>) -> R<()> {
+    print_info(format!("git sparse-checkout set --cone {}", directories.join(" ")));
+    let mut sparse_checkout_command = Command::new("git");
+      sparse_checkout_command
+      .current_dir(checkout_path.as_ref())
+      .arg("sparse-checkout")
+      .arg("set")
+      .arg("--cone")
+      .args(directories);
+
+    run_git(&mut sparse_checkout_command)?;
+
+    print_info("git checkout".to_owned());
+    let mut checkout_command = git_command(credentials);
+      checkout_command
+      .current_dir(checkout_path.as_ref())
+      .arg("checkout")
+      .arg("--quiet");
+
+    run_git(&mut checkout_command)
+}
+
+fn is_branch_in_worktree(mirror_path: &RepoMirrorPath, branch_name: &RepoBranchName) -> R<bool> {
+    let mut worktree_command = Command::new("git");
+      worktree_command
+      .arg("-C")
+      .arg(mirror_path.as_ref())
+      .arg("worktree")
+      .arg("list")
+      .arg("--porcelain");
+
+    let branch_line = format!("branch refs/heads/{}", branch_name);
+
+    get_git_output(&mut worktree_command).map(|worktrees| worktrees.lines().any(|line| line == branch_line))
+}
+
+/// Updates a checkout from a previous run to a specific commit. The commit is only fetched if the checkout doesn't already have it.
+/// Checkouts with local changes are not updated, so they are never lost. The diff and comment files from the previous run are ignored,
+/// so they are not local changes and are kept until they are regenerated. With sparse directories, only those of the commit are checked out.
+pub fn update_checkout(checkout_path: RepoCheckoutPath, fetch_ref: Option<String>, sha: &str, settings: CheckoutSettings) -> R<()> {
+    let mut status_command = Command::new("git");
+      status_command
+      .current_dir(checkout_path.as_ref())
+      .arg("status")
+      .arg("--porcelain");
+
+    let local_changes = get_git_output(&mut status_command)?;
+
+    if !local_changes.is_empty() {
+      return Err(PursError::GitError(format!("{} has local changes:\n{}\nCommit or remove them, or use --force-fresh to replace the checkout", checkout_path, local_changes)))
+    }
+
+    // The history left out by an earlier fetch with a depth is fetched, even if the checkout already has the commit
+    let needs_history = settings.clone_options.depth.is_none() && is_shallow(Path::new(checkout_path.as_ref()))?;
+
+    if needs_history || !has_commit(checkout_path.as_ref(), sha) {
+      let mut fetch_args = get_fetch_options(Path::new(checkout_path.as_ref()), settings.clone_options)?;
+      fetch_args.push("origin".to_owned());
+      fetch_args.extend(fetch_ref);
+
+      print_info(format!("git fetch {}", fetch_args.join(" ")));
+      let mut fetch_command = git_command(settings.credentials);
+        fetch_command
+        .current_dir(checkout_path.as_ref())
+        .arg("fetch")
+        .args(fetch_args);
+
+      run_git(&mut fetch_command)?;
+    }
+
+    print_info(format!("git reset --hard {}", sha));
+    // Files filtered out of a partial clone are fetched while resetting
+    let mut reset_command = git_command(settings.credentials);
+      reset_command
+      .current_dir(checkout_path.as_ref())
+      .arg("reset")
+      .arg("--hard")
+      .arg(sha);
+
+    run_git(&mut reset_command)?;
+
+    // The directories with changes may be different at the new commit
+    match settings.sparse_directories {
+      Some(directories) => sparse_checkout(&checkout_path, directories, settings.credentials),
+      None => Ok(())
+    }
+}
+
+fn has_commit<P: AsRef<Path>>(repository_path: P, sha: &str) -> bool {
+    Command::new("git")
+      .current_dir(repository_path)
+      .arg("cat-file")
+      .arg("-e")
+      .arg(format!("{}^{{commit}}", sha))
+      .stderr(Stdio::null())
+      .status()
+      .map(|status| status.success())
+      .unwrap_or(false)
+}
+
+/// Returns the url of the origin remote of a local repository
+pub fn get_origin_url(repo_path: &RepoCheckoutPath) -> R<String> {
+    let mut remote_command = Command::new("git");
+      remote_command
+      .current_dir(repo_path.as_ref())
+      .arg("remote")
+      .arg("get-url")
+      .arg("origin");
+
+    get_git_output(&mut remote_command)
+}
+
+/// Whether any tracked files of a local repository have changes. Untracked files are not included, as switching branches keeps them.
+pub fn has_uncommitted_changes(repo_path: &RepoCheckoutPath) -> R<bool> {
+    let mut status_command = Command::new("git");
+      status_command
+      .current_dir(repo_path.as_ref())
+      .arg("status")
+      .arg("--porcelain")
+      .arg("--untracked-files=no");
+
+    get_git_output(&mut status_command).map(|status| !status.is_empty())
+}
+
+/// Fetches the head of a PR from origin and switches to its head commit on a local branch. The branch is reset if it exists from a previous
+/// checkout of the PR. The head commit is used instead of the fetched ref, as the PR may have been updated since it was fetched.
+pub fn switch_to_pull_request(repo_path: &RepoCheckoutPath, pr_number: u64, branch_name: &RepoBranchName, sha: &str) -> R<()> {
+    let pull_ref = format!("pull/{}/head", pr_number);
+    print_info(format!("git fetch origin {}", pull_ref));
+    let mut fetch_command = Command::new("git");
+      fetch_command
+      .current_dir(repo_path.as_ref())
+      .arg("fetch")
+      .arg("origin")
+      .arg(pull_ref);
+
+    run_git(&mut fetch_command)?;
+
+    print_info(format!("git switch -C {} {}", branch_name, sha));
+    let mut switch_command = Command::new("git");
+      switch_command
+      .current_dir(repo_path.as_ref())
+      .arg("switch")
+      .arg("--quiet")
+      .arg("-C")
       .arg(branch_name.as_ref())
-      .arg(checkout_path.as_ref());
+      .arg(sha);
+
+    run_git(&mut switch_command)
+}
+
+/// Generates the diff of a PR in its checkout: the changes between the merge base of the base and head commits and the head commit.
+/// This is the same diff GitHub shows for a PR. Commits the checkout doesn't have, such as the base commit, are fetched first.
+pub fn get_local_diff(checkout_path: &RepoCheckoutPath, base_sha: &str, head_sha: &str, diff_options: &DiffOptions, credentials: Option<&GitHubToken>) -> R<String> {
+    let missing_commits: Vec<&str> =
+      [base_sha, head_sha]
+        .into_iter()
+        .filter(|sha| !has_commit(checkout_path.as_ref(), sha))
+        .collect();
+
+    if !missing_commits.is_empty() {
+      print_info(format!("git fetch origin {}", missing_commits.join(" ")));
+      let mut fetch_command = git_command(credentials);
+        fetch_command
+        .current_dir(checkout_path.as_ref())
+        .arg("fetch")
+        .arg("origin")
+        .args(missing_commits);
 
-    let git_clone_result = get_process_output(&mut command);
+      run_git(&mut fetch_command)?;
+    }
+
+    let mut merge_base_command = Command::new("git");
+      merge_base_command
+      .current_dir(checkout_path.as_ref())
+      .arg("merge-base")
+      .arg(base_sha)
+      .arg(head_sha);
+
+    let merge_base =
+      get_git_output(&mut merge_base_command)
+        .map_err(|e| {
+          let e1 = PursError::GitError(format!("Could not find the merge base of {} and {}. If the checkout is shallow, try a larger --depth", base_sha, head_sha));
+          PursError::MultipleErrors(vec![e1, e])
+        })?;
+
+    let context_option = diff_options.context_lines.map(|lines| format!("--unified={}", lines));
+    let whitespace_option = diff_options.ignore_whitespace.then_some("--ignore-all-space");
+
+    print_info(format!("git diff {} {}", merge_base, head_sha));
+    // Files filtered out of a partial clone are fetched while diffing
+    let mut diff_command = git_command(credentials);
+      diff_command
+      .current_dir(checkout_path.as_ref())
+      .arg("diff")
+      .arg("--no-color")
+      .arg("--no-ext-diff")
+      // The diff is parsed with the default prefixes, whatever diff.noprefix is set to
+      .arg("--src-prefix=a/")
+      .arg("--dst-prefix=b/")
+      .args(context_option)
+      .args(whitespace_option)
+      .arg(merge_base)
+      .arg(head_sha);
+
+    let output =
+      diff_command
+        .output()
+        .map_err(|e| PursError::ProcessError(NestedError::from(e)))?;
+
+    // Unlike the other git output, the diff can't be trimmed
+    if output.status.success() {
+      Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+      Err(PursError::GitError(String::from_utf8_lossy(&output.stderr).trim().to_owned()))
+    }
+}
 
-    let _ = match git_clone_result {
-      Ok(CmdOutput::Success) => {}, //Success will be returned at the end of the function
+/// The path of a file in the git directory of a checkout, such as info/exclude. Worktrees share this file with their mirror.
+pub fn get_git_path(checkout_path: &RepoCheckoutPath, git_path: &str) -> R<PathBuf> {
+    let mut rev_parse_command = Command::new("git");
+      rev_parse_command
+      .current_dir(checkout_path.as_ref())
+      .arg("rev-parse")
+      .arg("--git-path")
+      .arg(git_path);
+
+    // The path is relative to the checkout, unless the git directory is outside of it
+    get_git_output(&mut rev_parse_command).map(|path| Path::new(checkout_path.as_ref()).join(path))
+}
+
+/// Removes the ignored files of a checkout that match the pathspecs, such as the diff and comment files of a previous run
+pub fn remove_ignored_files(checkout_path: &RepoCheckoutPath, pathspecs: &[&str]) -> R<()> {
+    print_info(format!("git clean -fX -- {}", pathspecs.join(" ")));
+    let mut clean_command = Command::new("git");
+      clean_command
+      .current_dir(checkout_path.as_ref())
+      .arg("clean")
+      .arg("-fX")
+      .arg("--")
+      .args(pathspecs);
+
+    run_git(&mut clean_command)
+}
+
+/// Whether git tracks any files under a path of a checkout, such as a directory that purs would otherwise remove
+pub fn has_tracked_files(checkout_path: &RepoCheckoutPath, path: &Path) -> R<bool> {
+    let mut ls_files_command = Command::new("git");
+      ls_files_command
+      .current_dir(checkout_path.as_ref())
+      .arg("ls-files")
+      .arg("--")
+      .arg(path);
+
+    get_git_output(&mut ls_files_command).map(|files| !files.is_empty())
+}
+
+/// Checks that the checkout is at the expected commit, so the diff and comment files describe what is checked out
+pub fn verify_head(checkout_path: &RepoCheckoutPath, sha: &str) -> R<()> {
+    let head_sha = get_commit_sha(Path::new(checkout_path.as_ref()), "HEAD")?;
+
+    if head_sha == sha {
+      Ok(())
+    } else {
+      Err(PursError::GitError(format!("{} is at {} instead of the PR's head {}", checkout_path, head_sha, sha)))
+    }
+}
+
+fn get_commit_sha(repository_path: &Path, revision: &str) -> R<String> {
+    let mut rev_parse_command = Command::new("git");
+      rev_parse_command
+      .current_dir(repository_path)
+      .arg("rev-parse")
+      .arg("--verify")
+      .arg(format!("{}^{{commit}}", revision));
+
+    get_git_output(&mut rev_parse_command)
+}
+
+fn get_git_output(command: &mut Command) -> R<String> {
+    let output =
+      command
+        .output()
+        .map_err(|e| PursError::ProcessError(NestedError::from(e)))?;
+
+    if output.status.success() {
+      Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    } else {
+      Err(PursError::GitError(String::from_utf8_lossy(&output.stderr).trim().to_owned()))
+    }
+}
+
+/// Creates a git command that authenticates HTTPS requests with the token, if supplied.
+/// The credential helper is only configured for this command, so the token is never written to .git/config.
+fn git_command(credentials: Option<&GitHubToken>) -> Command {
+    let mut command = Command::new("git");
+
+    if let Some(token) = credentials {
+      command
+        // Remove any other configured credential helpers
+        .arg("-c")
+        .arg("credential.helper=")
+        .arg("-c")
+        .arg(format!("credential.helper={}", TOKEN_CREDENTIAL_HELPER))
+        .env(GIT_TOKEN_ENV_VAR, token.value())
+        // Fail instead of prompting if the token is rejected
+        .env("GIT_TERMINAL_PROMPT", "0");
+    }
+
+    command
+}
+
+fn run_git(command: &mut Command) -> R<()> {
+    let git_result = get_process_output(command);
+
+    match git_result {
+      Ok(CmdOutput::Success) => Ok(()),
       Ok(CmdOutput::Failure(exit_code)) => {
           match exit_code {
-              ExitCode::Code(code) => return Err(PursError::GitError(format!("Git exited with exit code: {}", code))),
-              ExitCode::Terminated => return Err(PursError::GitError("Git was terminated".to_string())),
+              ExitCode::Code(code) => Err(PursError::GitError(format!("Git exited with exit code: {}", code))),
+              ExitCode::Terminated => Err(PursError::GitError("Git was terminated".to_string())),
           }
       },
       Err(e2) => {
         let e1 = PursError::GitError("Error running Git".to_string());
-        return Err(PursError::MultipleErrors(vec![e1, e2]))
+        Err(PursError::MultipleErrors(vec![e1, e2]))
       },
-    };
-
-    Ok(())
+    }
 }
 
 fn get_process_output(command: &mut Command) -> R<CmdOutput> {
@@ -75,3 +571,8 @@ fn get_process_output(command: &mut Command) -> R<CmdOutput> {
         }
     })
 }
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
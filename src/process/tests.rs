@@ -0,0 +1,559 @@
+use crate::process::{clone_branch, clone_commit, update_checkout, get_origin_url, has_uncommitted_changes, switch_to_pull_request, verify_head, get_local_diff, get_git_path, remove_ignored_files, has_tracked_files};
+use crate::model::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+const DEFAULT_CLONE_OPTIONS: CloneOptions = CloneOptions { depth: None, filter: None, sparse_changed_only: false };
+
+fn git(dir: &Path, args: &[&str]) -> String {
+  let output =
+    Command::new("git")
+      .current_dir(dir)
+      .args(["-c", "user.name=purs", "-c", "user.email=purs@example.com"])
+      .args(args)
+      .output()
+      .unwrap();
+
+  assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+  String::from_utf8_lossy(&output.stdout).trim().to_owned()
+}
+
+/// Creates a repository with a commit on the feature branch and returns the SHA of that commit
+fn create_origin(dir: &Path) -> String {
+  git(dir, &["init", "-q"]);
+  fs::write(dir.join("README.md"), "readme").unwrap();
+  git(dir, &["add", "README.md"]);
+  git(dir, &["commit", "-q", "-m", "initial"]);
+  git(dir, &["checkout", "-q", "-b", "feature"]);
+  fs::write(dir.join("README.md"), "updated readme").unwrap();
+  git(dir, &["commit", "-q", "-am", "update"]);
+  git(dir, &["rev-parse", "HEAD"])
+}
+
+fn settings(credentials: Option<&GitHubToken>) -> CheckoutSettings<'_> {
+  CheckoutSettings {
+    clone_options: &DEFAULT_CLONE_OPTIONS,
+    sparse_directories: None,
+    credentials
+  }
+}
+
+fn mirror_path(dir: &Path) -> RepoMirrorPath {
+  RepoMirrorPath::new(dir.join("mirrors").join("owner").join("repo.git"))
+}
+
+#[test]
+fn clones_branch_without_storing_credentials() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+  let mirror = mirror_path(checkout.path());
+  let token = GitHubToken::new("ghp_secret");
+
+  let result =
+    clone_branch(
+      origin.path().to_string_lossy().to_string(),
+      &mirror,
+      RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string()),
+      RepoBranchName::new("feature".to_owned()),
+      &head_sha,
+      settings(Some(&token))
+    );
+
+  assert!(result.is_ok());
+  assert_eq!(git(&checkout_path, &["rev-parse", "HEAD"]), head_sha);
+  assert_eq!(git(&checkout_path, &["rev-parse", "--abbrev-ref", "HEAD"]), "feature");
+  assert_eq!(git(&checkout_path, &["rev-parse", "--abbrev-ref", "@{upstream}"]), "origin/feature");
+
+  let git_config = fs::read_to_string(mirror.as_ref().join("config")).unwrap();
+  assert!(!git_config.contains("ghp_secret"));
+  assert!(!git_config.contains("credential"));
+}
+
+#[test]
+fn shares_mirror_between_checkouts() {
+  let origin = tempdir().unwrap();
+  let first_sha = create_origin(origin.path());
+
+  let checkout = tempdir().unwrap();
+  let mirror = mirror_path(checkout.path());
+  let first_checkout_path = checkout.path().join("first");
+  let second_checkout_path = checkout.path().join("second");
+
+  clone_branch(
+    origin.path().to_string_lossy().to_string(),
+    &mirror,
+    RepoCheckoutPath::new(first_checkout_path.to_string_lossy().to_string()),
+    RepoBranchName::new("feature".to_owned()),
+    &first_sha,
+    settings(None)
+  ).unwrap();
+
+  fs::write(origin.path().join("README.md"), "second update").unwrap();
+  git(origin.path(), &["commit", "-q", "-am", "second update"]);
+  let second_sha = git(origin.path(), &["rev-parse", "HEAD"]);
+
+  let result =
+    clone_branch(
+      origin.path().to_string_lossy().to_string(),
+      &mirror,
+      RepoCheckoutPath::new(second_checkout_path.to_string_lossy().to_string()),
+      RepoBranchName::new("feature".to_owned()),
+      &second_sha,
+      settings(None)
+    );
+
+  assert!(result.is_ok());
+  assert_eq!(git(&first_checkout_path, &["rev-parse", "HEAD"]), first_sha);
+  assert_eq!(git(&second_checkout_path, &["rev-parse", "HEAD"]), second_sha);
+  assert_eq!(fs::read_to_string(second_checkout_path.join("README.md")).unwrap(), "second update");
+
+  // The branch is already checked out in the first worktree
+  assert_eq!(git(&second_checkout_path, &["rev-parse", "--abbrev-ref", "HEAD"]), "HEAD");
+
+  let worktrees = git(mirror.as_ref(), &["worktree", "list", "--porcelain"]);
+  assert!(worktrees.contains(&first_checkout_path.to_string_lossy().to_string()));
+  assert!(worktrees.contains(&second_checkout_path.to_string_lossy().to_string()));
+}
+
+#[test]
+fn recreates_deleted_worktree() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+  let mirror = mirror_path(checkout.path());
+
+  let clone = || {
+    clone_commit(
+      origin.path().to_string_lossy().to_string(),
+      &mirror,
+      RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string()),
+      Some("refs/heads/feature".to_owned()),
+      &head_sha,
+      settings(None)
+    )
+  };
+
+  clone().unwrap();
+  fs::remove_dir_all(&checkout_path).unwrap();
+
+  assert!(clone().is_ok());
+  assert_eq!(git(&checkout_path, &["rev-parse", "HEAD"]), head_sha);
+}
+
+#[test]
+fn clones_commit() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+
+  let result =
+    clone_commit(
+      origin.path().to_string_lossy().to_string(),
+      &mirror_path(checkout.path()),
+      RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string()),
+      Some("feature".to_owned()),
+      &head_sha,
+      settings(None)
+    );
+
+  assert!(result.is_ok());
+  assert_eq!(git(&checkout_path, &["rev-parse", "HEAD"]), head_sha);
+}
+
+#[test]
+fn clones_commit_from_pull_ref_when_branch_is_gone() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+
+  // GitHub keeps the PR head in refs/pull/<n>/head after the fork with the branch is deleted
+  git(origin.path(), &["update-ref", "refs/pull/7/head", &head_sha]);
+  git(origin.path(), &["checkout", "-q", "-"]);
+  git(origin.path(), &["branch", "-q", "-D", "feature"]);
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+
+  let result =
+    clone_commit(
+      origin.path().to_string_lossy().to_string(),
+      &mirror_path(checkout.path()),
+      RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string()),
+      Some("refs/pull/7/head".to_owned()),
+      &head_sha,
+      settings(None)
+    );
+
+  assert!(result.is_ok());
+  assert_eq!(git(&checkout_path, &["rev-parse", "HEAD"]), head_sha);
+}
+
+#[test]
+fn updates_existing_checkout_to_new_commit() {
+  let origin = tempdir().unwrap();
+  let first_sha = create_origin(origin.path());
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+  let repo_checkout_path = RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string());
+
+  clone_branch(
+    origin.path().to_string_lossy().to_string(),
+    &mirror_path(checkout.path()),
+    repo_checkout_path.clone(),
+    RepoBranchName::new("feature".to_owned()),
+    &first_sha,
+    settings(None)
+  ).unwrap();
+
+  assert_eq!(git(&checkout_path, &["rev-parse", "HEAD"]), first_sha);
+
+  // The PR is updated after the previous run
+  fs::write(origin.path().join("README.md"), "second update").unwrap();
+  git(origin.path(), &["commit", "-q", "-am", "second update"]);
+  let second_sha = git(origin.path(), &["rev-parse", "HEAD"]);
+
+  // Files generated by the previous run are excluded from git
+  let exclude_file = get_git_path(&repo_checkout_path, "info/exclude").unwrap();
+  fs::write(exclude_file, "*.diff\n").unwrap();
+  fs::write(checkout_path.join("README.md.diff"), "old diff").unwrap();
+
+  let result = update_checkout(repo_checkout_path, Some("feature".to_owned()), &second_sha, settings(None));
+
+  assert!(result.is_ok());
+  assert_eq!(git(&checkout_path, &["rev-parse", "HEAD"]), second_sha);
+  assert_eq!(fs::read_to_string(checkout_path.join("README.md")).unwrap(), "second update");
+  // They are removed when the files of this run are generated
+  assert!(checkout_path.join("README.md.diff").exists());
+}
+
+#[test]
+fn reuses_existing_checkout_with_commit() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+  let repo_checkout_path = RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string());
+
+  clone_branch(
+    origin.path().to_string_lossy().to_string(),
+    &mirror_path(checkout.path()),
+    repo_checkout_path.clone(),
+    RepoBranchName::new("feature".to_owned()),
+    &head_sha,
+    settings(None)
+  ).unwrap();
+
+  // Local changes are never discarded
+  fs::write(checkout_path.join("README.md"), "local change").unwrap();
+  fs::write(checkout_path.join("notes.txt"), "untracked notes").unwrap();
+
+  let result = update_checkout(repo_checkout_path.clone(), Some("feature".to_owned()), &head_sha, settings(None));
+
+  assert!(matches!(&result, Err(PursError::GitError(e)) if e.contains("--force-fresh") && e.contains("notes.txt")), "{:?}", result);
+  assert_eq!(fs::read_to_string(checkout_path.join("README.md")).unwrap(), "local change");
+  assert!(checkout_path.join("notes.txt").exists());
+
+  git(&checkout_path, &["checkout", "--", "README.md"]);
+  fs::remove_file(checkout_path.join("notes.txt")).unwrap();
+
+  // The origin is no longer available, so this only succeeds if nothing is fetched
+  fs::remove_dir_all(origin.path()).unwrap();
+
+  let result = update_checkout(repo_checkout_path, Some("feature".to_owned()), &head_sha, settings(None));
+
+  assert!(result.is_ok());
+  assert_eq!(git(&checkout_path, &["rev-parse", "HEAD"]), head_sha);
+  assert_eq!(fs::read_to_string(checkout_path.join("README.md")).unwrap(), "updated readme");
+}
+
+#[test]
+fn clones_shallow_partial_sparse_checkout() {
+  let origin = tempdir().unwrap();
+  create_origin(origin.path());
+
+  fs::create_dir_all(origin.path().join("changed").join("src")).unwrap();
+  fs::create_dir_all(origin.path().join("unchanged")).unwrap();
+  fs::create_dir_all(origin.path().join("build")).unwrap();
+  fs::write(origin.path().join("changed").join("src").join("Main.scala"), "main").unwrap();
+  fs::write(origin.path().join("unchanged").join("Other.scala"), "other").unwrap();
+  fs::write(origin.path().join("build").join("build.sbt"), "build").unwrap();
+  git(origin.path(), &["add", "."]);
+  git(origin.path(), &["commit", "-q", "-m", "add directories"]);
+  git(origin.path(), &["config", "uploadpack.allowFilter", "true"]);
+  let head_sha = git(origin.path(), &["rev-parse", "HEAD"]);
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+  let mirror = mirror_path(checkout.path());
+
+  let clone_options =
+    CloneOptions {
+      depth: CloneDepth::new(1),
+      filter: Some(CloneFilter::BlobNone),
+      sparse_changed_only: true
+    };
+
+  let sparse_directories = vec!["build".to_owned(), "changed/src".to_owned()];
+
+  let result =
+    clone_branch(
+      format!("file://{}", origin.path().to_string_lossy()),
+      &mirror,
+      RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string()),
+      RepoBranchName::new("feature".to_owned()),
+      &head_sha,
+      CheckoutSettings {
+        clone_options: &clone_options,
+        sparse_directories: Some(&sparse_directories),
+        credentials: None
+      }
+    );
+
+  assert!(result.is_ok());
+  assert_eq!(git(&checkout_path, &["rev-list", "--count", "HEAD"]), "1");
+  assert_eq!(git(mirror.as_ref(), &["config", "remote.origin.partialclonefilter"]), "blob:none");
+
+  assert!(checkout_path.join("README.md").exists());
+  assert!(checkout_path.join("changed").join("src").join("Main.scala").exists());
+  assert!(checkout_path.join("build").join("build.sbt").exists());
+  assert!(!checkout_path.join("unchanged").exists());
+  assert_eq!(git(&checkout_path, &["status", "--porcelain"]), "");
+
+  // The PR is updated to change other directories and the checkout is updated without a depth
+  fs::write(origin.path().join("unchanged").join("Other.scala"), "changed now").unwrap();
+  git(origin.path(), &["commit", "-q", "-am", "change other directory"]);
+  let second_sha = git(origin.path(), &["rev-parse", "HEAD"]);
+
+  let full_clone_options = CloneOptions { depth: None, ..clone_options };
+  let second_sparse_directories = vec!["unchanged".to_owned()];
+
+  let result =
+    update_checkout(
+      RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string()),
+      Some("feature".to_owned()),
+      &second_sha,
+      CheckoutSettings {
+        clone_options: &full_clone_options,
+        sparse_directories: Some(&second_sparse_directories),
+        credentials: None
+      }
+    );
+
+  assert!(result.is_ok(), "{:?}", result);
+  assert_eq!(git(mirror.as_ref(), &["rev-parse", "--is-shallow-repository"]), "false");
+  assert_eq!(git(&checkout_path, &["rev-list", "--count", "HEAD"]), "4");
+  assert!(checkout_path.join("unchanged").join("Other.scala").exists());
+  assert!(!checkout_path.join("changed").exists());
+  assert_eq!(git(&checkout_path, &["status", "--porcelain"]), "");
+}
+
+#[test]
+fn switches_existing_clone_to_pull_request() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+  git(origin.path(), &["update-ref", "refs/pull/7/head", &head_sha]);
+  git(origin.path(), &["checkout", "-q", "-"]);
+
+  let local = tempdir().unwrap();
+  let local_path = local.path().join("clone");
+  git(local.path(), &["clone", "-q", &origin.path().to_string_lossy(), "clone"]);
+  let repo_path = RepoCheckoutPath::new(local_path.to_string_lossy().to_string());
+
+  assert_eq!(get_origin_url(&repo_path).unwrap(), origin.path().to_string_lossy().to_string());
+  assert!(!has_uncommitted_changes(&repo_path).unwrap());
+
+  // Untracked files, such as build output, don't stop the switch
+  fs::write(local_path.join("build.log"), "build").unwrap();
+  assert!(!has_uncommitted_changes(&repo_path).unwrap());
+
+  let branch_name = RepoBranchName::new("purs/pr-7".to_owned());
+  assert!(switch_to_pull_request(&repo_path, 7, &branch_name, &head_sha).is_ok());
+  assert_eq!(git(&local_path, &["rev-parse", "HEAD"]), head_sha);
+  assert_eq!(git(&local_path, &["rev-parse", "--abbrev-ref", "HEAD"]), "purs/pr-7");
+
+  // The PR is checked out again once it has been updated
+  git(origin.path(), &["checkout", "-q", "feature"]);
+  fs::write(origin.path().join("README.md"), "second update").unwrap();
+  git(origin.path(), &["commit", "-q", "-am", "second update"]);
+  let second_sha = git(origin.path(), &["rev-parse", "HEAD"]);
+  git(origin.path(), &["update-ref", "refs/pull/7/head", &second_sha]);
+
+  assert!(switch_to_pull_request(&repo_path, 7, &branch_name, &second_sha).is_ok());
+  assert_eq!(git(&local_path, &["rev-parse", "HEAD"]), second_sha);
+}
+
+#[test]
+fn detects_uncommitted_changes() {
+  let origin = tempdir().unwrap();
+  create_origin(origin.path());
+  let repo_path = RepoCheckoutPath::new(origin.path().to_string_lossy().to_string());
+
+  fs::write(origin.path().join("README.md"), "uncommitted").unwrap();
+
+  assert!(has_uncommitted_changes(&repo_path).unwrap());
+}
+
+#[test]
+fn clones_pr_head_when_branch_has_moved() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+
+  // The branch is updated after the PR was fetched
+  fs::write(origin.path().join("README.md"), "second update").unwrap();
+  git(origin.path(), &["commit", "-q", "-am", "second update"]);
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+  let repo_checkout_path = RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string());
+
+  let result =
+    clone_branch(
+      origin.path().to_string_lossy().to_string(),
+      &mirror_path(checkout.path()),
+      repo_checkout_path.clone(),
+      RepoBranchName::new("feature".to_owned()),
+      &head_sha,
+      settings(None)
+    );
+
+  assert!(result.is_ok());
+  assert_eq!(git(&checkout_path, &["rev-parse", "HEAD"]), head_sha);
+  assert_eq!(git(&checkout_path, &["rev-parse", "--abbrev-ref", "HEAD"]), "HEAD");
+  assert!(verify_head(&repo_checkout_path, &head_sha).is_ok());
+}
+
+#[test]
+fn fails_verification_when_head_is_not_pr_head() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+  let initial_sha = git(origin.path(), &["rev-parse", "HEAD~1"]);
+  let repo_path = RepoCheckoutPath::new(origin.path().to_string_lossy().to_string());
+
+  assert!(verify_head(&repo_path, &head_sha).is_ok());
+  assert!(verify_head(&repo_path, &initial_sha).is_err());
+}
+
+#[test]
+fn generates_local_diff_from_merge_base() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+
+  // The base branch moves on after the PR branch was created
+  git(origin.path(), &["checkout", "-q", "-"]);
+  fs::write(origin.path().join("CHANGELOG.md"), "changes").unwrap();
+  git(origin.path(), &["add", "CHANGELOG.md"]);
+  git(origin.path(), &["commit", "-q", "-m", "changelog"]);
+  let base_sha = git(origin.path(), &["rev-parse", "HEAD"]);
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = checkout.path().join("checkout");
+  let repo_checkout_path = RepoCheckoutPath::new(checkout_path.to_string_lossy().to_string());
+
+  clone_branch(
+    origin.path().to_string_lossy().to_string(),
+    &mirror_path(checkout.path()),
+    repo_checkout_path.clone(),
+    RepoBranchName::new("feature".to_owned()),
+    &head_sha,
+    settings(None)
+  ).unwrap();
+
+  let diff = get_local_diff(&repo_checkout_path, &base_sha, &head_sha, &DiffOptions::default(), None).unwrap();
+
+  assert!(diff.starts_with("diff --git a/README.md b/README.md"));
+  assert!(diff.contains("+updated readme"));
+  assert!(!diff.contains("CHANGELOG.md"));
+}
+
+#[test]
+fn generates_local_diff_with_options() {
+  let origin = tempdir().unwrap();
+  create_origin(origin.path());
+  fs::write(origin.path().join("CHANGELOG.md"), "one\ntwo\nthree\nfour\nfive\n").unwrap();
+  git(origin.path(), &["add", "CHANGELOG.md"]);
+  git(origin.path(), &["commit", "-q", "-m", "changelog"]);
+  let base_sha = git(origin.path(), &["rev-parse", "HEAD"]);
+
+  fs::write(origin.path().join("README.md"), "updated  readme").unwrap();
+  fs::write(origin.path().join("CHANGELOG.md"), "one\ntwo\n3\nfour\nfive\n").unwrap();
+  git(origin.path(), &["commit", "-q", "-am", "change"]);
+  let head_sha = git(origin.path(), &["rev-parse", "HEAD"]);
+  let repo_path = RepoCheckoutPath::new(origin.path().to_string_lossy().to_string());
+
+  let diff_options =
+    DiffOptions {
+      source: DiffSource::Local,
+      context_lines: Some(0),
+      ignore_whitespace: true
+    };
+
+  let diff = get_local_diff(&repo_path, &base_sha, &head_sha, &diff_options, None).unwrap();
+
+  assert!(diff.contains("@@ -3 +3 @@"));
+  assert!(!diff.contains("\n two\n"));
+  assert!(!diff.contains("README.md"));
+}
+
+#[test]
+fn gets_exclude_file_of_mirror_for_worktree() {
+  let origin = tempdir().unwrap();
+  let head_sha = create_origin(origin.path());
+
+  let checkout = tempdir().unwrap();
+  let checkout_path = RepoCheckoutPath::new(checkout.path().join("checkout").to_string_lossy().to_string());
+  let mirror = mirror_path(checkout.path());
+
+  clone_commit(origin.path().to_string_lossy().to_string(), &mirror, checkout_path.clone(), Some("refs/heads/feature".to_owned()), &head_sha, settings(None)).unwrap();
+
+  let exclude_file = get_git_path(&checkout_path, "info/exclude").unwrap();
+
+  assert_eq!(exclude_file.canonicalize().unwrap(), mirror.as_ref().join("info").join("exclude").canonicalize().unwrap());
+}
+
+#[test]
+fn removes_only_ignored_files_matching_pathspecs() {
+  let origin = tempdir().unwrap();
+  create_origin(origin.path());
+  fs::write(origin.path().join(".git").join("info").join("exclude"), "*.diff\n*.log\n").unwrap();
+  fs::create_dir(origin.path().join("src")).unwrap();
+  fs::write(origin.path().join("src").join("main.rs.diff"), "diff").unwrap();
+  fs::write(origin.path().join("build.log"), "log").unwrap();
+  fs::write(origin.path().join("notes.txt"), "notes").unwrap();
+  let repo_path = RepoCheckoutPath::new(origin.path().to_string_lossy().to_string());
+
+  remove_ignored_files(&repo_path, &["*.diff", "*.comment"]).unwrap();
+
+  assert!(!origin.path().join("src").join("main.rs.diff").exists());
+  assert!(origin.path().join("build.log").exists());
+  assert!(origin.path().join("notes.txt").exists());
+  assert!(origin.path().join("README.md").exists());
+}
+
+#[test]
+fn detects_tracked_files_in_directory() {
+  let origin = tempdir().unwrap();
+  create_origin(origin.path());
+  fs::create_dir_all(origin.path().join("docs").join("guide")).unwrap();
+  fs::write(origin.path().join("docs").join("guide").join("index.md"), "guide").unwrap();
+  git(origin.path(), &["add", "."]);
+  git(origin.path(), &["commit", "-q", "-m", "add docs"]);
+
+  fs::create_dir_all(origin.path().join(".purs")).unwrap();
+  fs::write(origin.path().join(".purs").join("README.md.diff"), "diff").unwrap();
+
+  let repo_path = RepoCheckoutPath::new(origin.path().to_string_lossy().to_string());
+
+  assert!(has_tracked_files(&repo_path, Path::new("docs")).unwrap());
+  assert!(!has_tracked_files(&repo_path, Path::new(".purs")).unwrap());
+  assert!(!has_tracked_files(&repo_path, Path::new("missing")).unwrap());
+}
@@ -30,3 +30,52 @@ pub fn partition<T, E, I>(results: I) -> (Vec<T>, Vec<E>)
 
   (successes, errors)
 }
+
+pub fn dedup<T: PartialEq>(values: Vec<T>) -> Vec<T> {
+  let mut unique_values: Vec<T> = vec![];
+  values.into_iter().for_each(|v| {
+    if !unique_values.contains(&v) {
+      unique_values.push(v)
+    }
+  });
+
+  unique_values
+}
+
+/// Matches a value against a glob pattern where '*' matches any number of characters and '?' matches a single character
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+  let pattern_chars = pattern.chars().collect::<Vec<_>>();
+  let value_chars = value.chars().collect::<Vec<_>>();
+
+  let (mut p, mut v) = (0, 0);
+  let mut last_star: Option<(usize, usize)> = None;
+
+  while v < value_chars.len() {
+    match pattern_chars.get(p) {
+      Some('*') => {
+        last_star = Some((p, v));
+        p += 1;
+      },
+      Some(c) if *c == '?' || *c == value_chars[v] => {
+        p += 1;
+        v += 1;
+      },
+      _ => match last_star {
+        // Let the last '*' consume one more character and try again
+        Some((star_p, star_v)) => {
+          last_star = Some((star_p, star_v + 1));
+          p = star_p + 1;
+          v = star_v + 1;
+        },
+        None => return false
+      }
+    }
+  }
+
+  pattern_chars[p..].iter().all(|c| *c == '*')
+}
+
+// ---------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,25 @@
+use crate::tools::{glob_match, dedup};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn glob_matches_wildcards() {
+  assert!(glob_match("*", "anything"));
+  assert!(glob_match("service-*", "service-payments"));
+  assert!(glob_match("*-api", "billing-api"));
+  assert!(glob_match("svc-?", "svc-a"));
+  assert!(glob_match("*pay*", "service-payments"));
+  assert!(glob_match("exact", "exact"));
+}
+
+#[test]
+fn glob_does_not_match() {
+  assert!(!glob_match("service-*", "payments"));
+  assert!(!glob_match("*-api", "billing-api-old"));
+  assert!(!glob_match("svc-?", "svc-ab"));
+  assert!(!glob_match("exact", "exactly"));
+}
+
+#[test]
+fn dedup_keeps_first_occurrence_order() {
+  assert_eq!(dedup(vec![3, 1, 3, 2, 1]), vec![3, 1, 2])
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, TimeZone};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
@@ -19,9 +19,59 @@ use ratatui::{
     Frame, Terminal,
 };
 
-use crate::{console::*, model::{ValidatedPullRequest, PursError, UserInputError, R, ValidSelection, NestedError, Reviews, ReviewState, User, Mode}};
+use tokio::sync::mpsc::{UnboundedSender, UnboundedReceiver};
 
-pub fn render_tui(items: Vec<ValidatedPullRequest>) -> R<ValidSelection> {
+use crate::{console::*, model::{ValidatedPullRequest, PursError, UserInputError, R, ValidSelection, NestedError, User, Mode, PrSize, ProfileName, PullRequestState, PageOfPullRequests}};
+
+/// Requests more PRs to be loaded in the background and receives them once they are loaded
+pub struct PageLoader {
+  requests: UnboundedSender<()>,
+  pages: UnboundedReceiver<R<PageOfPullRequests>>,
+  has_more: bool,
+  loading: bool,
+  error: Option<String>
+}
+
+impl PageLoader {
+  pub fn new(requests: UnboundedSender<()>, pages: UnboundedReceiver<R<PageOfPullRequests>>, has_more: bool) -> Self {
+    PageLoader {
+      requests,
+      pages,
+      has_more,
+      loading: false,
+      error: None
+    }
+  }
+
+  fn request_more(&mut self) {
+    if self.has_more && !self.loading {
+      // If the loader has stopped there is nothing more to load
+      self.loading = self.requests.send(()).is_ok();
+      self.error = None;
+    }
+  }
+
+  fn try_receive(&mut self) -> Option<R<PageOfPullRequests>> {
+    let page = self.pages.try_recv().ok()?;
+    self.loading = false;
+    match &page {
+      Ok(loaded) => self.has_more = loaded.has_more,
+      Err(e) => self.error = Some(e.to_string())
+    }
+    Some(page)
+  }
+
+  fn status(&self) -> Option<String> {
+    match (&self.error, self.loading, self.has_more) {
+      (Some(error), _, _) => Some(format!("Could not load more PRs: {}", error)),
+      (None, true, _) => Some("Loading more PRs...".to_owned()),
+      (None, false, true) => Some("More PRs available".to_owned()),
+      (None, false, false) => None
+    }
+  }
+}
+
+pub fn render_tui(items: Vec<ValidatedPullRequest>, profile: Option<&ProfileName>, page_loader: PageLoader) -> R<ValidSelection> {
     // setup terminal
     enable_raw_mode().map_err(|e| PursError::TUIError(NestedError::from(e)))?;
     let mut stdout = io::stdout();
@@ -32,7 +82,12 @@ pub fn render_tui(items: Vec<ValidatedPullRequest>) -> R<ValidSelection> {
     // create app and run it
     let tick_rate = Duration::from_millis(250);
     let app = App::new(items);
-    let res = run_app(&mut terminal, app, tick_rate);
+    let title =
+      match profile {
+        Some(profile_name) => format!("Pull Requests [{}]", profile_name),
+        None => "Pull Requests".to_owned()
+      };
+    let res = run_app(&mut terminal, app, page_loader, &title, tick_rate);
 
     // restore terminal
     disable_raw_mode().map_err(|e| PursError::TUIError(NestedError::from(e)))?;
@@ -50,11 +105,23 @@ pub fn render_tui(items: Vec<ValidatedPullRequest>) -> R<ValidSelection> {
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     mut app: App<ValidatedPullRequest>,
+    mut page_loader: PageLoader,
+    title: &str,
     tick_rate: Duration,
 ) -> R<ValidSelection> {
     let mut last_tick = Instant::now();
     loop {
-        terminal.draw(|f| ui::<B>(f, &mut app)).map_err(|e| PursError::TUIError(NestedError::from(e)))?;
+        if let Some(Ok(page)) = page_loader.try_receive() {
+          app.items.items.extend(page.pull_requests)
+        }
+
+        let full_title =
+          match page_loader.status() {
+            Some(status) => format!("{} ({})", title, status),
+            None => title.to_owned()
+          };
+
+        terminal.draw(|f| ui::<B>(f, &mut app, &full_title)).map_err(|e| PursError::TUIError(NestedError::from(e)))?;
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
@@ -78,6 +145,11 @@ fn run_app<B: Backend>(
                     },
                     _ => {}
                 }
+
+                // Load the next page of PRs when the cursor reaches the end of the list
+                if app.items.is_last_selected() {
+                  page_loader.request_more()
+                }
             }
         }
         if last_tick.elapsed() >= tick_rate {
@@ -87,7 +159,8 @@ fn run_app<B: Backend>(
     }
 }
 
-fn ui<B: Backend>(f: &mut Frame<'_>, app: &mut App<ValidatedPullRequest>)
+#[allow(clippy::extra_unused_type_parameters)]
+fn ui<B: Backend>(f: &mut Frame<'_>, app: &mut App<ValidatedPullRequest>, title: &str)
 {
     // Create two chunks with equal horizontal screen space
     let chunks = Layout::default()
@@ -119,7 +192,7 @@ fn ui<B: Backend>(f: &mut Frame<'_>, app: &mut App<ValidatedPullRequest>)
     // Create a List from all list items and highlight the currently selected one
     let items =
       List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Pull Requests"))
+        .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(
             Style::default()
                 .fg(Color::White)
@@ -168,6 +241,7 @@ fn no_pr_details<'a>() -> Vec<Line<'a>> {
     help_line("How to to use purs"),
     help_line("------------------"),
     help_line("'Up'/'Down' arrows to select a PR"),
+    help_line("Select the last PR to load more PRs"),
     help_line("'Left' arrow to unselect a PR and view this menu"),
     help_line("'Enter' to checkout a PR for reviewing"),
     help_line("'e' to checkout a PR for editing"),
@@ -184,8 +258,9 @@ fn pr_details<'a>(pr: &ValidatedPullRequest) -> Vec<Line<'a>> {
   let owner_repo = details_key_value("Base Repository", pr.config_owner_repo.to_string());
   let title = details_key_value("Title", pr.title.clone());
   let pr_no = details_key_value("PR#", pr.pr_number.to_string());
-  let pr_url = details_key_value("Clone URL", pr.ssh_url.to_string());
-  let pr_repo = details_key_value("PR Repository", pr.repo_name.to_string());
+  let deleted = || "(deleted)".to_owned();
+  let pr_url = details_key_value("Clone URL", pr.ssh_url.as_ref().map(|u| u.to_string()).unwrap_or_else(deleted));
+  let pr_repo = details_key_value("PR Repository", pr.repo_name.as_ref().map(|r| r.to_string()).unwrap_or_else(deleted));
   let pr_branch = details_key_value("PR Branch", pr.branch_name.to_string());
   let head_sha = details_key_value("Head SHA", pr.head_sha.clone());
   let base_sha = details_key_value("Base SHA", pr.base_sha.clone());
@@ -202,10 +277,12 @@ fn pr_details<'a>(pr: &ValidatedPullRequest) -> Vec<Line<'a>> {
     details_key_value("Reviewers", sorted_names)
   };
 
-  let pr_diff_no = details_key_value("Changes", pr.diffs.0.len().to_string());
+  let pr_diff_no = details_key_value("Changes", pr.changed_files().map(|c| c.to_string()).unwrap_or_else(|| "Unknown".to_owned()));
 
   let draft = details_key_value("Draft", pr.draft.to_string());
 
+  let state = details_key_value("State", pr.state.to_string());
+
   let pr_owner = details_key_value("Owner", get_pr_owner(pr.pr_owner.clone()));
 
   let span_lines =
@@ -226,6 +303,7 @@ fn pr_details<'a>(pr: &ValidatedPullRequest) -> Vec<Line<'a>> {
       reviewer_names,
       pr_diff_no,
       draft,
+      state,
     ];
 
   let lines =
@@ -253,6 +331,7 @@ fn get_date_time<T: TimeZone>(date_time_option: Option<DateTime<T>>) -> String
     .unwrap_or_else(|| "-".to_owned())
 }
 
+#[allow(mismatched_lifetime_syntaxes)]
 fn details_key_value(key: &str, value: String) -> Vec<Span> {
   vec![
     Span::styled(key, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
@@ -262,16 +341,19 @@ fn details_key_value(key: &str, value: String) -> Vec<Span> {
 }
 
 
+#[allow(mismatched_lifetime_syntaxes)]
 fn pr_line(pr: &ValidatedPullRequest) -> Vec<Span> {
     let labels =
       vec![
         title(&pr.title),
-        pr_size(pr.diffs.0.len()),
+        pr_size(pr.size()),
         review_activity(pr.reviews.count()),
         comment_activity(pr.comments.count()),
         draft(pr.draft),
-        approved(pr.reviews.clone()),
-        is_old(pr.updated_at),
+        state(&pr.state),
+        head_repository_deleted(pr.is_head_repository_deleted()),
+        approved(pr.reviews.approval_count()),
+        is_old(pr.is_old()),
       ]
       .into_iter()
       .flatten()
@@ -296,16 +378,17 @@ fn separate_by<T: Clone>(items: Vec<T>, separator: T) -> Vec<T> {
   separated_items
 }
 
+#[allow(mismatched_lifetime_syntaxes)]
 fn title(title: &str) -> Option<Span> {
   Some(Span::raw(title.to_owned()))
 }
 
-fn pr_size<'a>(no_changes: usize) -> Option<Span<'a>> {
-    match no_changes {
-      0..=10  => None,
-      11..=20 => Some(Span::raw("🐕")),
-      21..=40 => Some(Span::raw("🐘")),
-      _       => Some(Span::raw("🐳"))
+fn pr_size<'a>(size: PrSize) -> Option<Span<'a>> {
+    match size {
+      PrSize::Small  => None,
+      PrSize::Medium => Some(Span::raw("🐕")),
+      PrSize::Large  => Some(Span::raw("🐘")),
+      PrSize::Huge   => Some(Span::raw("🐳"))
     }
 }
 
@@ -330,28 +413,28 @@ fn draft<'a>(is_draft: bool) -> Option<Span<'a>> {
   }
 }
 
-fn approved<'a>(reviews: Reviews) -> Option<Span<'a>> {
-  let approved_no =
-    reviews.reviews
-      .iter()
-      .filter_map(|r| {
-        match r.state {
-          ReviewState::Approved => Some("✅".to_owned()),
-          _ => None,
-        }
-      })
-      .collect::<Vec<_>>();
+fn state<'a>(pr_state: &PullRequestState) -> Option<Span<'a>> {
+  match pr_state {
+    PullRequestState::Open => None,
+    PullRequestState::Merged => Some(Span::raw("🔀")),
+    PullRequestState::Closed => Some(Span::raw("🚫")),
+  }
+}
 
-  Some(Span::raw(approved_no.join("")))
+fn head_repository_deleted<'a>(deleted: bool) -> Option<Span<'a>> {
+  match deleted {
+    true => Some(Span::raw("👻")),
+    false => None
+  }
 }
 
-fn is_old<'a>(time_opt: Option<DateTime<Utc>>) -> Option<Span<'a>> {
-  time_opt.and_then(|t|{
-    let is_older_than_a_week = Utc::now().signed_duration_since(t).num_days() > 7;
-    if is_older_than_a_week {
-      Some(Span::raw("🦕".to_owned()))
-    } else {
-      None
-    }
-  })
+fn approved<'a>(no_approvals: usize) -> Option<Span<'a>> {
+  Some(Span::raw("✅".repeat(no_approvals)))
+}
+
+fn is_old<'a>(old: bool) -> Option<Span<'a>> {
+  match old {
+    true => Some(Span::raw("🦕".to_owned())),
+    false => None
+  }
 }